@@ -1,4 +1,6 @@
-use typst::syntax::{LinkedNode, Source, SyntaxKind, SyntaxNode};
+use std::str::FromStr;
+
+use typst::syntax::{ast::{self, AstNode}, package::PackageSpec, LinkedNode, Source, Span, SyntaxKind, SyntaxNode};
 
 use crate::console_log;
 
@@ -26,7 +28,8 @@ pub fn new_lines(node: &LinkedNode) -> usize {
 }
 
 /// Collects all previous nodes of a given node that match the specified kinds.
-/// It stops collecting when it encounters a `Space` or `Parbreak` node with more than `max_nl` new lines.
+/// It stops collecting when it encounters a `Space` or `Parbreak` node with more than `max_nl` new lines,
+/// or any other node that is not trivia, e.g. the previous parameter.
 pub fn collect_prev_nodes<'b>(
     node: &'b LinkedNode<'b>,
     kinds: &[SyntaxKind],
@@ -38,11 +41,10 @@ pub fn collect_prev_nodes<'b>(
     while let Some(n) = current_node {
         console_log!("Collecting previous node: {:?}", n);
         match n.kind() {
-            SyntaxKind::Space | SyntaxKind::Parbreak => {
-                if new_lines(&n) > max_nl {
-                    break;
-                }
-            }
+            SyntaxKind::Space | SyntaxKind::Parbreak if new_lines(&n) > max_nl => break,
+            // The `#` in front of an embedded let binding.
+            SyntaxKind::Hash => {}
+            kind if !kind.is_trivia() && !kinds.contains(&kind) => break,
             _ => {}
         }
         current_node = n.prev_sibling_verbose();
@@ -61,11 +63,11 @@ pub fn is_let_binding<'b>(node: &'b LinkedNode<'b>) -> Option<&'b LinkedNode<'b>
     {
         if let Some(parent) = node.parent() {
             if parent.kind() == SyntaxKind::LetBinding {
-                return Some(&parent);
+                return Some(parent);
             } else if parent.kind() == SyntaxKind::Closure {
                 if let Some(grandparent) = parent.parent() {
                     if grandparent.kind() == SyntaxKind::LetBinding {
-                        return Some(&grandparent);
+                        return Some(grandparent);
                     }
                 }
             }
@@ -93,34 +95,47 @@ pub fn extract_text(nodes: &[LinkedNode]) -> Vec<String> {
     nodes.iter().map(|node| node.text().to_string()).collect()
 }
 
+/// Returns the source text of a closure parameter, e.g. `x`, `fill: red` or `..rest`.
+pub fn param_label(node: &LinkedNode) -> String {
+    match node.kind() {
+        SyntaxKind::Named | SyntaxKind::Spread => node.get().clone().into_text().to_string(),
+        _ => node.text().to_string(),
+    }
+}
+
+/// A name together with the raw comment lines preceding it.
+pub type Documented = (String, Vec<String>);
+
 pub fn parse_let_binding<'b>(
     node: &'b LinkedNode<'b>,
-) -> Option<((String, Vec<String>), Vec<(String, Vec<String>)>)> {
+) -> Option<(Documented, Vec<Documented>)> {
     if let Some(binding) = is_let_binding(node) {
         let possible_binding_docs = collect_prev_nodes(binding, &[SyntaxKind::LineComment], 1);
         let (name, args) = if let Some(closure) = child_by_kind(binding, SyntaxKind::Closure) {
-            let name =
-                child_by_kind(&closure, SyntaxKind::Ident).and_then(|n| Some(n.text().to_string()))?;
+            // `let f(x) = ..` names the closure, `let f = (x) => ..` only the binding.
+            let name = child_by_kind(&closure, SyntaxKind::Ident)
+                .or_else(|| child_by_kind(binding, SyntaxKind::Ident))
+                .map(|n| n.text().to_string())?;
             let mut ret = Vec::new();
             if let Some(params) = child_by_kind(&closure, SyntaxKind::Params) {
-                    let args = children_by_kind(&params, &[SyntaxKind::Ident, SyntaxKind::Named]);
+                    let args = children_by_kind(&params, &[SyntaxKind::Ident, SyntaxKind::Named, SyntaxKind::Spread]);
                     if !args.is_empty() {
                         ret = args.iter()
                                 .map(|n| {
-                                    let txt = n.text().to_string();
+                                    let txt = param_label(n);
                                     let possible_arg_docs =
                                         collect_prev_nodes(n, &[SyntaxKind::LineComment], 1);
                                     let possible_arg_docs_text = extract_text(&possible_arg_docs);
 
                                     (txt, possible_arg_docs_text)
                                 })
-                                .collect::<Vec<(String, Vec<String>)>>();
+                                .collect::<Vec<Documented>>();
                     }
                 }
             (name, ret)
         } else {
             let name =
-                child_by_kind(binding, SyntaxKind::Ident).and_then(|n| Some(n.text().to_string()))?;
+                child_by_kind(binding, SyntaxKind::Ident).map(|n| n.text().to_string())?;
             (name, Vec::new())
         };
 
@@ -130,6 +145,170 @@ pub fn parse_let_binding<'b>(
     }
 }
 
+/// The argument of a call the cursor is in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ActiveArg {
+    /// The n-th positional argument.
+    Positional(usize),
+    /// A named argument.
+    Named(String),
+}
+
+/// Determines the argument the cursor is in, if it is inside the parentheses of the `Args` node.
+fn active_arg(args: &LinkedNode, cursor: usize) -> Option<ActiveArg> {
+    let lparen = args.children().next().filter(|c| c.kind() == SyntaxKind::LeftParen)?;
+    if cursor <= lparen.offset() {
+        return None;
+    }
+
+    let mut positional = 0;
+    let mut current = None;
+    for child in args.children().skip(1) {
+        if child.offset() >= cursor {
+            break;
+        }
+        match child.kind() {
+            SyntaxKind::RightParen => return None,
+            SyntaxKind::Comma => {
+                if matches!(current, Some(ActiveArg::Positional(_))) {
+                    positional += 1;
+                }
+                current = None;
+            }
+            SyntaxKind::Named => {
+                current = child
+                    .cast::<ast::Named>()
+                    .map(|named| ActiveArg::Named(named.name().get().to_string()));
+            }
+            kind if kind.is_trivia() => {}
+            _ => current = Some(ActiveArg::Positional(positional)),
+        }
+    }
+
+    Some(current.unwrap_or(ActiveArg::Positional(positional)))
+}
+
+/// Finds the innermost function call whose parentheses contain the cursor.
+/// Returns the callee and the argument the cursor is in.
+pub fn enclosing_call<'b>(leaf: &LinkedNode<'b>, cursor: usize) -> Option<(LinkedNode<'b>, ActiveArg)> {
+    let mut node = leaf.clone();
+    loop {
+        if node.kind() == SyntaxKind::Args {
+            if let Some(active) = active_arg(&node, cursor) {
+                let call = node.parent().filter(|call| call.kind() == SyntaxKind::FuncCall)?;
+                return Some((call.children().next()?, active));
+            }
+        }
+        node = node.parent()?.clone();
+    }
+}
+
+/// Whether the node is an identifier that names a binding, rather than an argument name like `fill:`
+/// in a call or a field like `.len`.
+pub fn is_binding_reference(node: &LinkedNode) -> bool {
+    if !matches!(node.kind(), SyntaxKind::Ident | SyntaxKind::MathIdent) {
+        return false;
+    }
+    match node.parent() {
+        Some(parent) if parent.kind() == SyntaxKind::Named => {
+            parent.parent_kind() != Some(SyntaxKind::Args) || node.index() != 0
+        }
+        Some(parent) if parent.kind() == SyntaxKind::FieldAccess => node.index() == 0,
+        _ => true,
+    }
+}
+
+/// Whether the node is the identifier of a closure parameter.
+pub fn is_param_binding(node: &LinkedNode) -> bool {
+    if node.kind() != SyntaxKind::Ident {
+        return false;
+    }
+    match node.parent() {
+        Some(parent) if parent.kind() == SyntaxKind::Params => true,
+        Some(parent) if parent.kind() == SyntaxKind::Named => {
+            node.index() == 0 && parent.parent_kind() == Some(SyntaxKind::Params)
+        }
+        Some(parent) if parent.kind() == SyntaxKind::Spread => parent.parent_kind() == Some(SyntaxKind::Params),
+        _ => false,
+    }
+}
+
+/// Returns the field access if the node is the accessed field, like `add` in `a.add`.
+pub fn accessed_field<'a>(node: &LinkedNode<'a>) -> Option<LinkedNode<'a>> {
+    let parent = node.parent().filter(|parent| parent.kind() == SyntaxKind::FieldAccess)?;
+    (node.kind() == SyntaxKind::Ident && node.index() != 0).then(|| parent.clone())
+}
+
+/// Returns the callee of the call if the node is the name of a named argument, like `add` for `b` in `add(b: 1)`.
+pub fn named_arg_callee<'a>(node: &LinkedNode<'a>) -> Option<LinkedNode<'a>> {
+    let named = node.parent().filter(|parent| parent.kind() == SyntaxKind::Named && node.index() == 0)?;
+    let args = named.parent().filter(|args| args.kind() == SyntaxKind::Args)?;
+    let call = args.parent().filter(|call| call.kind() == SyntaxKind::FuncCall)?;
+    call.children().next()
+}
+
+/// Returns the identifier a closure parameter's function is bound to, the name of `#let f(x) = ..` or
+/// `#let f = (x) => ..`.
+pub fn param_owner<'a>(param: &LinkedNode<'a>) -> Option<LinkedNode<'a>> {
+    let mut node = param.parent()?.clone();
+    while node.kind() != SyntaxKind::Closure {
+        node = node.parent()?.clone();
+    }
+    let ident = |node: &LinkedNode<'a>| node.children().find(|child| child.kind() == SyntaxKind::Ident);
+    ident(&node).or_else(|| ident(node.parent().filter(|parent| parent.kind() == SyntaxKind::LetBinding)?))
+}
+
+/// Returns the span of the last top-level let binding of the name, which is what a module exports under it.
+pub fn top_level_binding(source: &Source, name: &str) -> Option<Span> {
+    source
+        .root()
+        .children()
+        .filter_map(|child| child.cast::<ast::LetBinding>())
+        .flat_map(|binding| binding.kind().bindings())
+        .rfind(|ident| ident.get() == name)
+        .map(|ident| ident.span())
+}
+
+/// Returns the path of a `#import` or `#include` if it is given as a string literal.
+pub fn import_path(node: &SyntaxNode) -> Option<String> {
+    let source = if let Some(import) = node.cast::<ast::ModuleImport>() {
+        import.source()
+    } else {
+        node.cast::<ast::ModuleInclude>()?.source()
+    };
+
+    match source {
+        ast::Expr::Str(path) => Some(path.get().to_string()),
+        _ => None,
+    }
+}
+
+/// Collects all packages imported or included anywhere below the given node.
+pub fn collect_package_imports(node: &SyntaxNode, packages: &mut Vec<PackageSpec>) {
+    if let Some(spec) = import_path(node)
+        .filter(|path| path.starts_with('@'))
+        .and_then(|path| PackageSpec::from_str(&path).ok())
+    {
+        packages.push(spec);
+    }
+
+    for child in node.children() {
+        collect_package_imports(child, packages);
+    }
+}
+
+/// Collects the values of all string literals at or below the given node, e.g. the paths of an import or an
+/// `image` call.
+pub fn collect_string_literals(node: &SyntaxNode, strings: &mut Vec<String>) {
+    if let Some(string) = node.cast::<ast::Str>() {
+        strings.push(string.get().to_string());
+    }
+
+    for child in node.children() {
+        collect_string_literals(child, strings);
+    }
+}
+
 fn recursive_print_ast(node: &SyntaxNode, indent: usize) {
     let indent_str = "  ".repeat(indent);
     let mut debug_text = node.text().clone();
@@ -144,13 +323,13 @@ fn recursive_print_ast(node: &SyntaxNode, indent: usize) {
     );
 
     for child in node.children() {
-        recursive_print_ast(&child, indent + 1);
+        recursive_print_ast(child, indent + 1);
     }
 }
 
 pub fn debug_print_ast(source: Source) {
     let root = source.root();
     console_log!("AST Debug Print:");
-    recursive_print_ast(&root, 0);
+    recursive_print_ast(root, 0);
     console_log!("End of AST Debug Print");
 }
@@ -0,0 +1,356 @@
+use std::ops::Range;
+
+use typst::syntax::{LinkedNode, Source, SyntaxKind, SyntaxNode};
+
+use crate::{ast::new_lines, typst::wasm::structs::format::TypstCoreFormatOptions};
+
+/// Whether the node is a line or block comment.
+fn is_comment(node: &SyntaxNode) -> bool {
+    matches!(node.kind(), SyntaxKind::LineComment | SyntaxKind::BlockComment)
+}
+
+/// The texts of all leaves that carry meaning, used to check that formatting only touched whitespace and separators.
+fn significant_leaves(node: &SyntaxNode, leaves: &mut Vec<String>) {
+    if node.children().len() == 0
+        && !node.kind().is_trivia()
+        && !matches!(node.kind(), SyntaxKind::Comma | SyntaxKind::Semicolon)
+    {
+        leaves.push(node.text().to_string());
+    }
+    for child in node.children() {
+        significant_leaves(child, leaves);
+    }
+}
+
+/// Removes the spaces at the end of lines, which never matter in markup.
+fn strip_trailing_spaces(text: &str) -> String {
+    let mut lines = text.split('\n').collect::<Vec<_>>();
+    let last = lines.pop().unwrap_or_default();
+    let mut stripped = lines
+        .into_iter()
+        .map(|line| format!("{}\n", line.trim_end_matches([' ', '\t'])))
+        .collect::<String>();
+    stripped.push_str(last);
+    stripped
+}
+
+/// Prints code, keeping markup as it is apart from the code embedded in it.
+struct Printer<'a> {
+    options: &'a TypstCoreFormatOptions,
+    /// The indentation of the line the printed code starts on.
+    base_indent: &'a str,
+    /// The column the printed code starts at.
+    start_column: usize,
+    out: String,
+}
+
+impl<'a> Printer<'a> {
+    fn new(options: &'a TypstCoreFormatOptions, base_indent: &'a str, start_column: usize) -> Self {
+        Self {
+            options,
+            base_indent,
+            start_column,
+            out: String::new(),
+        }
+    }
+
+    /// A printer for trying out a layout at the current column.
+    fn trial(&self) -> Self {
+        Self::new(self.options, self.base_indent, self.column())
+    }
+
+    fn column(&self) -> usize {
+        match self.out.rfind('\n') {
+            Some(index) => self.out[index + 1..].chars().count(),
+            None => self.start_column + self.out.chars().count(),
+        }
+    }
+
+    fn fits(&self, text: &str) -> bool {
+        !text.contains('\n') && self.column() + text.chars().count() <= self.options.max_width
+    }
+
+    fn newline(&mut self, depth: usize) {
+        self.out.push('\n');
+        self.out.push_str(self.base_indent);
+        self.out.push_str(&" ".repeat(depth * self.options.indent_width));
+    }
+
+    fn verbatim(&mut self, node: &SyntaxNode) {
+        self.out.push_str(&node.clone().into_text());
+    }
+
+    fn node(&mut self, node: &SyntaxNode, depth: usize) {
+        if node.children().len() == 0 {
+            self.out.push_str(node.text());
+            return;
+        }
+        // Comments are hard to move around, so anything directly containing one is kept as it is.
+        if node.children().any(is_comment) {
+            self.verbatim(node);
+            return;
+        }
+
+        match node.kind() {
+            SyntaxKind::Args
+            | SyntaxKind::Array
+            | SyntaxKind::Dict
+            | SyntaxKind::Params
+            | SyntaxKind::Destructuring => self.list(node, depth),
+            SyntaxKind::CodeBlock => self.code_block(node, depth),
+            SyntaxKind::ContentBlock | SyntaxKind::Markup => self.markup(node, depth),
+            SyntaxKind::Named | SyntaxKind::Keyed => self.pair(node, depth),
+            SyntaxKind::Unary => {
+                let mut children = node.children().filter(|child| !child.kind().is_trivia());
+                if let (Some(op), Some(expr)) = (children.next(), children.next()) {
+                    self.node(op, depth);
+                    if op.kind() == SyntaxKind::Not {
+                        self.out.push(' ');
+                    }
+                    self.node(expr, depth);
+                }
+            }
+            SyntaxKind::FuncCall
+            | SyntaxKind::FieldAccess
+            | SyntaxKind::Parenthesized
+            | SyntaxKind::Spread
+            | SyntaxKind::ImportItemPath => {
+                for child in node.children().filter(|child| !child.kind().is_trivia()) {
+                    self.node(child, depth);
+                }
+            }
+            SyntaxKind::ImportItems if node.children().any(|child| child.kind() == SyntaxKind::LeftParen) => {
+                self.verbatim(node)
+            }
+            SyntaxKind::Binary
+            | SyntaxKind::Closure
+            | SyntaxKind::LetBinding
+            | SyntaxKind::DestructAssignment
+            | SyntaxKind::SetRule
+            | SyntaxKind::ShowRule
+            | SyntaxKind::Contextual
+            | SyntaxKind::Conditional
+            | SyntaxKind::WhileLoop
+            | SyntaxKind::ForLoop
+            | SyntaxKind::ModuleImport
+            | SyntaxKind::ImportItems
+            | SyntaxKind::RenamedImportItem
+            | SyntaxKind::ModuleInclude
+            | SyntaxKind::FuncReturn => self.words(node, depth),
+            _ => self.verbatim(node),
+        }
+    }
+
+    /// Prints the children separated by single spaces, except before punctuation and argument lists.
+    fn words(&mut self, node: &SyntaxNode, depth: usize) {
+        for (i, child) in node.children().filter(|child| !child.kind().is_trivia()).enumerate() {
+            let attached = matches!(
+                child.kind(),
+                SyntaxKind::Colon | SyntaxKind::Comma | SyntaxKind::Semicolon | SyntaxKind::Args | SyntaxKind::Params
+            );
+            if i > 0 && !attached {
+                self.out.push(' ');
+            }
+            self.node(child, depth);
+        }
+    }
+
+    /// Prints `name: value`.
+    fn pair(&mut self, node: &SyntaxNode, depth: usize) {
+        let children = node.children().filter(|child| !child.kind().is_trivia()).collect::<Vec<_>>();
+        match children.as_slice() {
+            [name, colon, value] if colon.kind() == SyntaxKind::Colon => {
+                self.node(name, depth);
+                self.out.push_str(": ");
+                self.node(value, depth);
+            }
+            _ => self.verbatim(node),
+        }
+    }
+
+    /// Prints a parenthesized list on one line if it fits, otherwise one item per line.
+    fn list(&mut self, node: &SyntaxNode, depth: usize) {
+        let children = node.children().collect::<Vec<_>>();
+        let Some(close) = children.iter().position(|child| child.kind() == SyntaxKind::RightParen) else {
+            // Arguments given only as trailing content blocks.
+            if node.kind() == SyntaxKind::Args {
+                for child in children.iter().filter(|child| !child.kind().is_trivia()) {
+                    self.node(child, depth);
+                }
+            } else {
+                self.verbatim(node);
+            }
+            return;
+        };
+        if children.first().map(|child| child.kind()) != Some(SyntaxKind::LeftParen) {
+            self.verbatim(node);
+            return;
+        }
+
+        let items = children[1..close]
+            .iter()
+            .filter(|child| !child.kind().is_trivia() && child.kind() != SyntaxKind::Comma)
+            .copied()
+            .collect::<Vec<_>>();
+        // The empty dictionary `(:)`.
+        if items.iter().any(|item| item.kind() == SyntaxKind::Colon) {
+            self.verbatim(node);
+            return;
+        }
+        // `(x,)` is an array, while `(x)` is just `x`.
+        let needs_comma = items.len() == 1 && matches!(node.kind(), SyntaxKind::Array | SyntaxKind::Destructuring);
+
+        let mut flat = self.trial();
+        flat.out.push('(');
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                flat.out.push_str(", ");
+            }
+            flat.node(item, depth);
+        }
+        if needs_comma {
+            flat.out.push(',');
+        }
+        flat.out.push(')');
+
+        if items.is_empty() || self.fits(&flat.out) {
+            self.out.push_str(&flat.out);
+        } else {
+            self.out.push('(');
+            for (i, item) in items.iter().enumerate() {
+                self.newline(depth + 1);
+                self.node(item, depth + 1);
+                if i + 1 < items.len() || self.options.trailing_comma || needs_comma {
+                    self.out.push(',');
+                }
+            }
+            self.newline(depth);
+            self.out.push(')');
+        }
+
+        for child in children[close + 1..].iter().filter(|child| !child.kind().is_trivia()) {
+            self.node(child, depth);
+        }
+    }
+
+    /// Prints a code block with one statement per line, short single line blocks stay on one line.
+    fn code_block(&mut self, node: &SyntaxNode, depth: usize) {
+        let Some(code) = node.children().find(|child| child.kind() == SyntaxKind::Code) else {
+            self.out.push_str("{}");
+            return;
+        };
+        if code.children().any(is_comment) {
+            self.verbatim(node);
+            return;
+        }
+
+        let statements = code
+            .children()
+            .filter(|child| !child.kind().is_trivia() && child.kind() != SyntaxKind::Semicolon)
+            .collect::<Vec<_>>();
+        if statements.is_empty() {
+            self.out.push_str("{}");
+            return;
+        }
+
+        if let [statement] = statements.as_slice() {
+            if !node.clone().into_text().contains('\n') {
+                let mut flat = self.trial();
+                flat.out.push_str("{ ");
+                flat.node(statement, depth);
+                flat.out.push_str(" }");
+                if self.fits(&flat.out) {
+                    self.out.push_str(&flat.out);
+                    return;
+                }
+            }
+        }
+
+        self.out.push('{');
+        for statement in statements {
+            self.newline(depth + 1);
+            self.node(statement, depth + 1);
+        }
+        self.newline(depth);
+        self.out.push('}');
+    }
+
+    /// Prints markup as it is, formatting only the code embedded with `#`.
+    fn markup(&mut self, node: &SyntaxNode, depth: usize) {
+        let mut embedded = false;
+        for child in node.children() {
+            if embedded {
+                self.node(child, depth);
+            } else if matches!(child.kind(), SyntaxKind::Space | SyntaxKind::Parbreak) {
+                self.out.push_str(&strip_trailing_spaces(child.text()));
+            } else if child.children().len() > 0 && child.kind() != SyntaxKind::Raw {
+                self.markup(child, depth);
+            } else {
+                self.out.push_str(child.text());
+            }
+            embedded = child.kind() == SyntaxKind::Hash;
+        }
+    }
+}
+
+/// Collects the formatting edits for a node and everything below it that intersects the range.
+fn collect_edits(
+    source: &Source,
+    node: &LinkedNode,
+    range: &Range<usize>,
+    options: &TypstCoreFormatOptions,
+    edits: &mut Vec<(Range<usize>, String)>,
+) {
+    let mut embedded = false;
+    for child in node.children() {
+        let is_embedded = embedded;
+        embedded = child.kind() == SyntaxKind::Hash;
+        if child.range().end < range.start || child.offset() > range.end {
+            continue;
+        }
+
+        let original = child.get().clone().into_text();
+        if is_embedded {
+            let line_start = source.line_to_byte(source.byte_to_line(child.offset()).unwrap_or_default()).unwrap_or_default();
+            let line = &source.text()[line_start..child.offset()];
+            let base_indent = &line[..line.len() - line.trim_start_matches([' ', '\t']).len()];
+
+            let mut printer = Printer::new(options, base_indent, line.chars().count());
+            printer.node(child.get(), 0);
+
+            // Only keep the result if nothing but whitespace and separators changed.
+            let (mut before, mut after) = (Vec::new(), Vec::new());
+            significant_leaves(child.get(), &mut before);
+            significant_leaves(&typst::syntax::parse_code(&printer.out), &mut after);
+            if printer.out != original.as_str() && before == after {
+                edits.push((child.range(), printer.out));
+            }
+        } else if matches!(child.kind(), SyntaxKind::Space | SyntaxKind::Parbreak) && new_lines(&child) > 0 {
+            let stripped = strip_trailing_spaces(child.text());
+            if stripped != original.as_str() {
+                edits.push((child.range(), stripped));
+            }
+        } else if child.kind() != SyntaxKind::Raw {
+            collect_edits(source, &child, range, options, edits);
+        }
+    }
+}
+
+/// Formats the code embedded in a source and removes trailing spaces from its markup.
+///
+/// Only touches the parts intersecting the range, or everything if there is none. Sources with syntax errors are
+/// left alone. Returns the replaced byte ranges with their new text, in source order.
+pub fn format_source(
+    source: &Source,
+    range: Option<Range<usize>>,
+    options: &TypstCoreFormatOptions,
+) -> Vec<(Range<usize>, String)> {
+    if source.root().erroneous() {
+        return Vec::new();
+    }
+
+    let range = range.unwrap_or(0..source.len_bytes());
+    let mut edits = Vec::new();
+    collect_edits(source, &LinkedNode::new(source.root()), &range, options, &mut edits);
+    edits
+}
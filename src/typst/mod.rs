@@ -6,17 +6,25 @@ use std::{
 use atomic_refcell::AtomicRefCell;
 use chrono::{DateTime, Local};
 use source_file::SourceFile;
+use target::CompileTarget;
 use typst::{
-    layout::PagedDocument,
+    diag::FileError,
+    foundations::Bytes,
     syntax::FileId,
     text::{Font, FontBook},
     utils::LazyHash,
     Library,
 };
-use wasm::structs::package::TypstCorePackage;
+use wasm::structs::{
+    format::TypstCoreFormatOptions, package::TypstCorePackage, pdf::TypstCorePdfOptions,
+    semantic::TypstCoreSemanticTokens, png::TypstCorePngOptions,
+    registry::TypstCoreRegistry,
+};
 use wasm_bindgen::prelude::wasm_bindgen;
 
+mod format;
 mod source_file;
+mod target;
 mod tidy;
 mod typst_core;
 pub mod wasm;
@@ -30,13 +38,37 @@ pub struct TypstCore {
 
     sources: Arc<AtomicRefCell<HashMap<FileId, SourceFile>>>,
 
+    files: Arc<AtomicRefCell<HashMap<FileId, Bytes>>>,
+
     fonts: Mutex<Vec<Font>>,
 
-    root: Option<FileId>,
+    /// The number of fonts at the start of `fonts` that are bundled with the core.
+    builtin_fonts: usize,
 
-    now: OnceLock<DateTime<Local>>,
+    /// The named documents compiled from the sources.
+    targets: Mutex<HashMap<String, CompileTarget>>,
+
+    /// The root of the target being compiled or queried, returned by `World::main`.
+    main: Mutex<Option<FileId>>,
+
+    /// The sources read while compiling, recorded only during a compile.
+    accessed: Mutex<Option<HashSet<FileId>>>,
+
+    /// The files that failed to load while compiling and why, recorded only during a compile.
+    file_failures: Mutex<Option<HashMap<FileId, FileError>>>,
 
-    last_doc: Mutex<Option<PagedDocument>>,
+    now: OnceLock<DateTime<Local>>,
 
     packages: Mutex<HashSet<TypstCorePackage>>,
+
+    registries: HashMap<String, TypstCoreRegistry>,
+
+    pdf_options: TypstCorePdfOptions,
+
+    png_options: TypstCorePngOptions,
+
+    format_options: TypstCoreFormatOptions,
+
+    /// The last semantic tokens sent for each source, to compute deltas against.
+    semantic_tokens: Mutex<HashMap<FileId, TypstCoreSemanticTokens>>,
 }
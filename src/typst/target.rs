@@ -0,0 +1,40 @@
+use std::collections::HashSet;
+
+use typst::{layout::PagedDocument, syntax::FileId};
+
+use super::wasm::structs::font::TypstCoreMissingFont;
+
+/// The target compiled by `compile` and set by `set_root`.
+pub const DEFAULT_TARGET: &str = "default";
+
+/// A document compiled from its own root file, all targets of a core share its sources.
+pub struct CompileTarget {
+    pub root: FileId,
+
+    pub last_doc: Option<PagedDocument>,
+
+    /// The font families the compile of `last_doc` could not find.
+    pub missing_fonts: Vec<TypstCoreMissingFont>,
+
+    /// The page hashes of the last `OutputFormat::SvgIncremental` compile.
+    pub svg_page_hashes: Vec<u128>,
+
+    /// The sources read by the last compile, to find the targets a file belongs to.
+    pub dependencies: HashSet<FileId>,
+
+    /// Orders the targets by their last compile, zero if never compiled.
+    pub compiled_at: u64,
+}
+
+impl CompileTarget {
+    pub fn new(root: FileId) -> Self {
+        Self {
+            root,
+            last_doc: None,
+            missing_fonts: Vec::new(),
+            svg_page_hashes: Vec::new(),
+            dependencies: HashSet::new(),
+            compiled_at: 0,
+        }
+    }
+}
@@ -0,0 +1,257 @@
+//! Parses doc comments in the style of [tidy](https://github.com/Mc-Zen/tidy).
+
+use crate::ast::Documented;
+
+/// A parameter of a documented function.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TidyParam {
+    /// The parameter as written in the signature, e.g. `stroke: 1pt`.
+    pub label: String,
+    pub name: String,
+    pub default: Option<String>,
+    pub variadic: bool,
+    pub types: Vec<String>,
+    pub description: String,
+}
+
+impl TidyParam {
+    /// Splits a signature label like `..args` or `stroke: 1pt` into its parts.
+    fn from_label(label: &str) -> Self {
+        let (name, default) = match label.split_once(':') {
+            Some((name, default)) => (name.trim(), Some(default.trim().to_string())),
+            None => (label.trim(), None),
+        };
+        Self {
+            label: label.to_string(),
+            name: name.trim_start_matches("..").to_string(),
+            default,
+            variadic: name.starts_with(".."),
+            ..Self::default()
+        }
+    }
+
+    /// Parses a parameter list entry like `- body (content, none): What to put in the box.`
+    fn from_list_item(line: &str) -> Option<Self> {
+        let rest = line.trim_start().strip_prefix("- ")?.trim_start();
+        let end = rest.find([' ', '(', ':']).unwrap_or(rest.len());
+        let name = &rest[..end];
+        if !typst::syntax::is_ident(name.trim_start_matches("..")) {
+            return None;
+        }
+
+        let mut rest = rest[end..].trim_start();
+        let mut types = Vec::new();
+        if let Some(inner) = rest.strip_prefix('(') {
+            let close = inner.find(')')?;
+            types = split_types(&inner[..close]);
+            rest = inner[close + 1..].trim_start();
+        }
+        let description = rest.strip_prefix(':')?.trim().to_string();
+
+        Some(Self {
+            types,
+            description,
+            ..Self::from_label(name)
+        })
+    }
+}
+
+/// The parsed doc comment of a binding.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct TidyDocs {
+    pub description: String,
+    pub params: Vec<TidyParam>,
+    /// The types after `->`, the return type of a function or the type of a variable.
+    pub returns: Vec<String>,
+    /// The code of the `@example` blocks and `` ```example `` fences.
+    pub examples: Vec<String>,
+}
+
+impl TidyDocs {
+    /// Parses the lines of a comment block, including the comment markers.
+    ///
+    /// Only `///` lines form a doc comment. Without any, the `//` lines are taken as a plain description.
+    pub fn parse(lines: &[String]) -> Self {
+        Self::parse_with_params(lines, &[])
+    }
+
+    /// Parses a comment block like `parse`, taking the list entries that name one of `params` as their docs.
+    /// Other list entries, like `- Note: ...`, stay in the description.
+    fn parse_with_params(lines: &[String], params: &[String]) -> Self {
+        let mut docs = Self::default();
+        let doc_lines = lines
+            .iter()
+            .filter_map(|line| line.strip_prefix("///"))
+            .map(strip_comment_line)
+            .collect::<Vec<_>>();
+        if doc_lines.is_empty() {
+            docs.description = lines
+                .iter()
+                .filter_map(|line| line.strip_prefix("//"))
+                .map(strip_comment_line)
+                .collect::<Vec<_>>()
+                .join("\n")
+                .trim()
+                .to_string();
+            return docs;
+        }
+
+        let mut description = Vec::new();
+        let mut example: Option<Vec<&str>> = None;
+        let mut fenced = false;
+        // Whether an indented line continues the description of the last parameter.
+        let mut in_param = false;
+        for line in doc_lines {
+            let trimmed = line.trim();
+            if let Some(code) = example.as_mut() {
+                let ends = if fenced {
+                    trimmed == "```"
+                } else {
+                    trimmed.is_empty() || trimmed.starts_with('@') || trimmed.starts_with("->")
+                };
+                if !ends {
+                    code.push(line);
+                    continue;
+                }
+                docs.examples.push(dedent(code));
+                example = None;
+                if fenced {
+                    continue;
+                }
+            }
+
+            if let Some(first) = trimmed.strip_prefix("@example") {
+                fenced = false;
+                example = Some(Vec::from_iter((!first.trim().is_empty()).then(|| first.trim())));
+            } else if trimmed.starts_with("```example") {
+                fenced = true;
+                example = Some(Vec::new());
+            } else if let Some(types) = trimmed.strip_prefix("->") {
+                docs.returns = split_types(types);
+            } else if let Some(param) = TidyParam::from_list_item(line).filter(|param| params.contains(&param.name)) {
+                docs.params.push(param);
+                in_param = true;
+            } else if let Some(param) = docs.params.last_mut().filter(|_| in_param && line.starts_with(' ') && !trimmed.is_empty()) {
+                // An indented line continues the description of the parameter above.
+                param.description.push(' ');
+                param.description.push_str(trimmed);
+            } else {
+                description.push(line);
+                in_param = false;
+            }
+        }
+        if let Some(code) = example {
+            docs.examples.push(dedent(&code));
+        }
+
+        docs.description = description.join("\n").trim().to_string();
+        docs
+    }
+
+    /// Parses the doc comments of a binding and its parameters, as returned by `parse_let_binding`.
+    ///
+    /// A parameter's own doc comment takes precedence over its entry in the parameter list of the binding.
+    pub fn from_binding((_, lines): &Documented, params: &[Documented]) -> Self {
+        let params = params
+            .iter()
+            .map(|(label, lines)| (TidyParam::from_label(label), lines))
+            .collect::<Vec<_>>();
+        let names = params.iter().map(|(param, _)| param.name.clone()).collect::<Vec<_>>();
+        let mut docs = Self::parse_with_params(lines, &names);
+        if params.is_empty() {
+            return docs;
+        }
+
+        let mut listed = std::mem::take(&mut docs.params);
+        docs.params = params
+            .into_iter()
+            .map(|(mut param, lines)| {
+                let own = Self::parse(lines);
+                let entry = listed
+                    .iter()
+                    .position(|entry| entry.name == param.name)
+                    .map(|index| listed.remove(index))
+                    .unwrap_or_default();
+                param.description = if own.description.is_empty() { entry.description } else { own.description };
+                param.types = if own.returns.is_empty() { entry.types } else { own.returns };
+                param
+            })
+            .collect();
+        docs
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    /// Renders the docs as markdown, with the parameters, return types and examples in their own sections.
+    pub fn to_markdown(&self) -> String {
+        let mut sections = Vec::new();
+        if !self.description.is_empty() {
+            sections.push(self.description.clone());
+        }
+
+        let params = self
+            .params
+            .iter()
+            .filter(|param| !param.types.is_empty() || !param.description.is_empty())
+            .map(|param| {
+                let mut item = format!("- `{}`", param.name);
+                if !param.types.is_empty() {
+                    item.push_str(&format!(" ({})", code_list(&param.types)));
+                }
+                if !param.description.is_empty() {
+                    item.push_str(&format!(": {}", param.description));
+                }
+                item
+            })
+            .collect::<Vec<_>>();
+        if !params.is_empty() {
+            sections.push(format!("**Parameters**\n{}", params.join("\n")));
+        }
+
+        if !self.returns.is_empty() {
+            sections.push(format!("**Returns** {}", code_list(&self.returns)));
+        }
+
+        for example in &self.examples {
+            sections.push(format!("**Example**\n```typ\n{}\n```", example));
+        }
+        sections.join("\n\n")
+    }
+}
+
+/// Removes the single space usually following the comment marker and any trailing whitespace.
+fn strip_comment_line(line: &str) -> &str {
+    line.strip_prefix(' ').unwrap_or(line).trim_end()
+}
+
+/// Splits a type annotation like `int, float` or `int | none`.
+fn split_types(types: &str) -> Vec<String> {
+    types
+        .split([',', '|'])
+        .map(str::trim)
+        .filter(|ty| !ty.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Formats types as `` `int` | `float` ``.
+fn code_list(types: &[String]) -> String {
+    types.iter().map(|ty| format!("`{}`", ty)).collect::<Vec<_>>().join(" | ")
+}
+
+/// Joins the lines of an example, removing the indentation they share.
+fn dedent(lines: &[&str]) -> String {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -1,82 +1,71 @@
-use std::{
-    collections::HashMap,
-    io::{Cursor, Read},
-};
+use std::io::{Cursor, Read};
 
 use flate2::read::GzDecoder;
 use typst::{
-    diag::{FileError, FileResult},
-    syntax::{package::PackageSpec, FileId, LinkedNode, Span, VirtualPath},
+    diag::{FileError, FileResult, PackageError},
+    foundations::Bytes,
+    layout::PagedDocument,
+    syntax::{package::PackageSpec, FileId, LinkedNode, Side, Source, Span, SyntaxKind, VirtualPath},
+};
+use typst_ide::Definition;
+
+use crate::ast::{
+    accessed_field, collect_package_imports, import_path, is_binding_reference, is_param_binding, named_arg_callee,
+    param_owner, parse_let_binding, top_level_binding,
 };
-use wasm_bindgen::closure;
 
-use crate::{console_log, utils::fetch};
+use super::{source_file::SourceFile, tidy::TidyDocs, world::FocusedWorld, TypstCore};
 
-use super::{source_file::SourceFile, TypstCore};
+/// Wraps an archive related error message into a `PackageError::MalformedArchive`.
+fn malformed_archive(message: String) -> FileError {
+    FileError::Package(PackageError::MalformedArchive(Some(message.into())))
+}
 
 /// Extracts the path from a tar entry, handling potential errors.
 fn extract_entry_path(entry: &tar::Entry<GzDecoder<Cursor<Vec<u8>>>>) -> FileResult<String> {
     Ok(entry
         .path()
-        .map_err(|e| {
-            FileError::Other(Some(
-                format!("Failed to read package entry path: {}", e).into(),
-            ))
-        })?
+        .map_err(|e| malformed_archive(format!("Failed to read package entry path: {}", e)))?
         .to_string_lossy()
         .into_owned())
 }
 
-/// Reads the contents of a tar entry into a String, handling potential errors.
-fn read_entry_contents(entry: &mut tar::Entry<GzDecoder<Cursor<Vec<u8>>>>) -> FileResult<String> {
+/// Reads the raw contents of a tar entry, handling potential errors.
+fn read_entry_contents(entry: &mut tar::Entry<GzDecoder<Cursor<Vec<u8>>>>) -> FileResult<Vec<u8>> {
     let mut bytes = Vec::new();
-    entry.read_to_end(&mut bytes).map_err(|e| {
-        FileError::Other(Some(
-            format!("Failed to read package entry bytes: {}", e).into(),
-        ))
-    })?;
+    entry
+        .read_to_end(&mut bytes)
+        .map_err(|e| malformed_archive(format!("Failed to read package entry bytes: {}", e)))?;
 
-    Ok(String::from_utf8_lossy(&bytes).to_string())
+    Ok(bytes)
 }
 
-/// Fetches a Typst package from the official repository and extracts its source files.
+/// Extracts the files of a Typst package from its gzipped tarball.
 ///
-/// This function:
-/// 1. Downloads the package tarball from packages.typst.org
-/// 2. Extracts all files from the archive
-/// 3. Loads the content as SourceFile objects
+/// The tarball is fetched by the host, so this never blocks on network I/O.
 ///
-/// Returns a map of FileId to SourceFile for all files in the package.
-fn fetch_package(package: &PackageSpec) -> FileResult<HashMap<FileId, SourceFile>> {
-    // Build the URL for the package tarball
-    let url = format!(
-        "https://packages.typst.org/preview/{}-{}.tar.gz",
-        package.name, package.version
-    );
-
-    // Download and prepare the archive for extraction
-    let response = fetch(&url);
-    let cursor = Cursor::new(response);
+/// Returns the FileId and raw contents of all files in the package.
+fn extract_package(package: &PackageSpec, tarball: Vec<u8>) -> FileResult<Vec<(FileId, Vec<u8>)>> {
+    let cursor = Cursor::new(tarball);
     let gz_decoder = GzDecoder::new(cursor);
     let mut archive = tar::Archive::new(gz_decoder);
 
     // Get all entries from the archive, handling potential errors
-    let entries = archive.entries().map_err(|e| {
-        FileError::Other(Some(
-            format!("Failed to read package archive: {}", e).into(),
-        ))
-    })?;
+    let entries = archive
+        .entries()
+        .map_err(|e| malformed_archive(format!("Failed to read package archive: {}", e)))?;
 
     // Process each file in the archive
-    let mut sources = HashMap::new();
-    for entry_result in entries.filter(|e| {
+    let mut files = Vec::new();
+    for entry_result in entries {
+        // An entry that cannot be read means the archive is corrupt, rather than something to skip
+        let mut entry = entry_result
+            .map_err(|e| malformed_archive(format!("Failed to read package entry: {}", e)))?;
+
         // Only process actual files (not directories)
-        e.is_ok() && e.as_ref().unwrap().header().entry_type().is_file()
-    }) {
-        // Extract file metadata and contents
-        let mut entry = entry_result.map_err(|e| {
-            FileError::Other(Some(format!("Failed to read package entry: {}", e).into()))
-        })?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
 
         // Get the path within the archive
         let path = extract_entry_path(&entry)?;
@@ -85,58 +74,249 @@ fn fetch_package(package: &PackageSpec) -> FileResult<HashMap<FileId, SourceFile
         let file_id = FileId::new(Some(package.clone()), VirtualPath::new(path));
 
         // Read the file contents
-        let text = read_entry_contents(&mut entry)?;
+        let bytes = read_entry_contents(&mut entry)?;
+        files.push((file_id, bytes));
+    }
 
-        // Create and store the source file
-        let source_file = SourceFile::new(file_id, text);
-        sources.insert(file_id, source_file);
+    if files.is_empty() {
+        return Err(malformed_archive("Package archive contains no files".to_string()));
     }
 
-    Ok(sources)
+    Ok(files)
 }
 
 impl TypstCore {
-    pub fn retrieve_source(&self, id: FileId) -> FileResult<SourceFile> {
-        match id.package() {
-            Some(package) => {
-                console_log!("Retrieving source for id: {:?}, {:?}", id, package);
-                if self.packages.lock().unwrap().contains(&(package.clone().into())) {
-                    let sources = self.sources.borrow();
-                    if let Some(source) = sources.get(&id) {
-                        Ok(source.clone())
-                    } else {
-                        Err(typst::diag::FileError::NotFound(
-                            id.vpath().as_rooted_path().to_path_buf(),
-                        ))
-                    }
+    /// Fails if the file belongs to a package that is not installed.
+    fn check_package(&self, id: FileId) -> FileResult<()> {
+        if let Some(package) = id.package() {
+            if !self.packages.lock().unwrap().contains(&(package.clone().into())) {
+                return Err(FileError::Package(if self.registries.contains_key(package.namespace.as_str()) {
+                    PackageError::Other(Some(
+                        format!("Package {} is not installed, resolve imports before compiling", package).into(),
+                    ))
                 } else {
-                    let fetched_sources = fetch_package(package)?;
-                    {
-                        let mut sources = self.sources.borrow_mut();
-                        for (id, source) in fetched_sources {
-                            sources.insert(id, source.clone());
-                        }
-                        self.packages.lock().unwrap().insert(package.clone().into());
-                    }
-                    if let Some(source) = self.sources.borrow().get(&id) {
-                        Ok(source.clone())
-                    } else {
-                        Err(typst::diag::FileError::NotFound(
-                            id.vpath().as_rooted_path().to_path_buf(),
-                        ))
-                    }
-                }
+                    PackageError::NotFound(package.clone())
+                }));
+            }
+        }
+        Ok(())
+    }
+
+    /// Records a failed file access while compiling, to tag the diagnostic it turns into with its kind.
+    pub fn record_failure<T>(&self, id: FileId, result: FileResult<T>) -> FileResult<T> {
+        if let (Err(error), Some(failures)) = (&result, self.file_failures.lock().unwrap().as_mut()) {
+            failures.insert(id, error.clone());
+        }
+        result
+    }
+
+    pub fn retrieve_source(&self, id: FileId) -> FileResult<SourceFile> {
+        self.check_package(id)?;
+
+        let sources = self.sources.borrow();
+        if let Some(source) = sources.get(&id) {
+            Ok(source.clone())
+        } else if self.files.borrow().contains_key(&id) {
+            Err(FileError::NotSource)
+        } else {
+            Err(typst::diag::FileError::NotFound(
+                id.vpath().as_rooted_path().to_path_buf(),
+            ))
+        }
+    }
+
+    /// Retrieves the raw bytes of a file, binary files take precedence over sources.
+    pub fn retrieve_file(&self, id: FileId) -> FileResult<Bytes> {
+        self.check_package(id)?;
+
+        if let Some(bytes) = self.files.borrow().get(&id) {
+            return Ok(bytes.clone());
+        }
+        Ok(self.retrieve_source(id)?.bytes())
+    }
+
+    /// Extracts a package tarball into the virtual file system and marks the package as installed.
+    ///
+    /// `.typ` files are stored as sources and everything else as binary files. Nothing is stored if any entry
+    /// cannot be read or a `.typ` file is not valid UTF-8.
+    pub fn install_package_tarball(&self, package: &PackageSpec, tarball: Vec<u8>) -> FileResult<()> {
+        let mut sources = Vec::new();
+        let mut files = Vec::new();
+        for (id, bytes) in extract_package(package, tarball)? {
+            if id.vpath().as_rootless_path().extension().is_some_and(|ext| ext == "typ") {
+                let text = String::from_utf8(bytes).map_err(|_| FileError::InvalidUtf8)?;
+                sources.push((id, SourceFile::new(id, text)));
+            } else {
+                files.push((id, Bytes::new(bytes)));
             }
-            None => {
-                let sources = self.sources.borrow();
-                if let Some(source) = sources.get(&id) {
-                    Ok(source.clone())
+        }
+
+        self.sources.borrow_mut().extend(sources);
+        self.files.borrow_mut().extend(files);
+        self.packages.lock().unwrap().insert(package.clone().into());
+        Ok(())
+    }
+
+    /// Returns all packages imported by a source in the virtual file system that are not installed yet.
+    pub fn missing_packages(&self) -> Vec<PackageSpec> {
+        let mut imported = Vec::new();
+        for source_file in self.sources.borrow().values() {
+            collect_package_imports(source_file.source.root(), &mut imported);
+        }
+
+        let installed = self.packages.lock().unwrap();
+        let mut missing = Vec::new();
+        for spec in imported {
+            if !installed.contains(&(spec.clone().into())) && !missing.contains(&spec) {
+                missing.push(spec);
+            }
+        }
+        missing
+    }
+
+    /// Picks the target a file is analyzed in, returning the target's last document and its root.
+    ///
+    /// Prefers a target rooted at the file, then the most recently compiled one that read it.
+    /// A file outside of every target is analyzed as its own root. `World::main` is left alone, it only
+    /// changes when compiling; queries see the root as main through `focused`.
+    pub fn focus(&self, id: FileId) -> (Option<PagedDocument>, FileId) {
+        let targets = self.targets.lock().unwrap();
+        let target = targets
+            .values()
+            .filter(|target| target.root == id)
+            .max_by_key(|target| target.compiled_at)
+            .or_else(|| {
+                targets
+                    .values()
+                    .filter(|target| target.dependencies.contains(&id))
+                    .max_by_key(|target| target.compiled_at)
+            });
+        (
+            target.and_then(|target| target.last_doc.clone()),
+            target.map_or(id, |target| target.root),
+        )
+    }
+
+    /// Returns the span of the binding the identifier at the cursor refers to.
+    pub fn binding_at(&self, source: &Source, cursor: usize) -> Option<Span> {
+        let root = LinkedNode::new(source.root());
+        let (doc, main) = self.focus(source.id());
+        let world = self.focused(main);
+        [Side::After, Side::Before].into_iter().find_map(|side| {
+            // Parameters are not resolved by `typst_ide::definition`, they are their own binding.
+            if let Some(leaf) = root.leaf_at(cursor, side.clone()).filter(is_param_binding) {
+                return Some(leaf.span());
+            }
+            match typst_ide::definition(&world, doc.as_ref(), source, cursor, side) {
+                Some(Definition::Span(span)) if !span.is_detached() => Some(span),
+                _ => None,
+            }
+        })
+    }
+
+    /// Parses the doc comment of the let binding the identifier at the cursor refers to.
+    pub fn binding_docs(&self, source: &Source, cursor: usize) -> Option<TidyDocs> {
+        let (doc, main) = self.focus(source.id());
+        let world = self.focused(main);
+        let Some(Definition::Span(span)) = typst_ide::definition(&world, doc.as_ref(), source, cursor, Side::After) else {
+            return None;
+        };
+        self.resolve_span(span, |node, _, _| {
+            parse_let_binding(&node).map(|(binding, params)| TidyDocs::from_binding(&binding, &params))
+        })
+        .ok()
+        .flatten()
+        .flatten()
+    }
+
+    /// Finds every identifier in the virtual file system that refers to the binding at `target`,
+    /// including the binding itself.
+    ///
+    /// Besides plain identifiers this includes fields of imported modules like `a.add` and, for a parameter,
+    /// the named arguments of calls to its function.
+    pub fn find_references(&self, target: Span) -> Vec<Span> {
+        let Ok(Some((name, owner))) = self.resolve_span(target, |node, _, _| {
+            let owner = param_owner(&node).filter(|_| is_param_binding(&node)).map(|owner| owner.span());
+            (node.text().clone(), owner)
+        }) else {
+            return Vec::new();
+        };
+
+        fn collect<'a>(node: LinkedNode<'a>, name: &str, found: &mut Vec<LinkedNode<'a>>) {
+            if matches!(node.kind(), SyntaxKind::Ident | SyntaxKind::MathIdent) && node.text() == name {
+                found.push(node.clone());
+            }
+            for child in node.children() {
+                collect(child, name, found);
+            }
+        }
+
+        let sources = self
+            .sources
+            .borrow()
+            .values()
+            .map(|source_file| source_file.source())
+            .collect::<Vec<_>>();
+
+        let mut references = Vec::new();
+        for source in &sources {
+            let (doc, main) = self.focus(source.id());
+            let world = self.focused(main);
+            let mut candidates = Vec::new();
+            collect(LinkedNode::new(source.root()), &name, &mut candidates);
+            references.extend(candidates.into_iter().filter_map(|node| {
+                if node.span() == target {
+                    return Some(target);
+                }
+                let resolved = if is_param_binding(&node) {
+                    // A parameter of the same name shadows the target instead of referring to it.
+                    None
+                } else if let Some(callee) = named_arg_callee(&node) {
+                    owner.filter(|owner| self.resolve_expr(&world, doc.as_ref(), source, &callee) == Some(*owner)).map(|_| target)
+                } else if let Some(access) = accessed_field(&node) {
+                    self.resolve_expr(&world, doc.as_ref(), source, &access)
+                } else if is_binding_reference(&node) {
+                    self.resolve_expr(&world, doc.as_ref(), source, &node)
                 } else {
-                    Err(typst::diag::FileError::NotFound(
-                        id.vpath().as_rooted_path().to_path_buf(),
-                    ))
+                    None
+                };
+                (resolved == Some(target)).then(|| node.span())
+            }));
+        }
+        references
+    }
+
+    /// Returns the span of the binding an identifier or a field access on an imported module refers to.
+    fn resolve_expr(
+        &self,
+        world: &FocusedWorld,
+        doc: Option<&PagedDocument>,
+        source: &Source,
+        node: &LinkedNode,
+    ) -> Option<Span> {
+        match node.kind() {
+            SyntaxKind::Ident | SyntaxKind::MathIdent => {
+                match typst_ide::definition(world, doc, source, node.offset(), Side::After) {
+                    Some(Definition::Span(span)) if !span.is_detached() => Some(span),
+                    _ => None,
                 }
             }
+            SyntaxKind::FieldAccess => {
+                let module = node.children().next().filter(|module| module.kind() == SyntaxKind::Ident)?;
+                let field = node.children().next_back()?;
+                let import = self.resolve_expr(world, doc, source, &module)?;
+                let id = self
+                    .resolve_span(import, |node, id, _| {
+                        let import = std::iter::successors(Some(node), |node| node.parent().cloned())
+                            .find(|node| node.kind() == SyntaxKind::ModuleImport)?;
+                        import_path(&import).filter(|path| !path.starts_with('@')).map(|path| id.join(&path))
+                    })
+                    .ok()
+                    .flatten()
+                    .flatten()?;
+                top_level_binding(&self.retrieve_source(id).ok()?.source(), field.text())
+            }
+            _ => None,
         }
     }
 
@@ -166,7 +346,7 @@ impl TypstCore {
 
     pub fn get_source_file(&self, path: String) -> FileResult<SourceFile> {
         let id = FileId::new(None, VirtualPath::new(&path));
-        self.retrieve_source(id).map_err(|e| {
+        self.retrieve_source(id).map_err(|_| {
             typst::diag::FileError::NotFound(
                 id.vpath().as_rooted_path().to_path_buf(),
             )
@@ -0,0 +1,297 @@
+use std::{ops::Range, path::Component};
+
+use typst::{
+    syntax::{ast, FileId, LinkedNode, Side, Source, SyntaxKind, VirtualPath},
+    World,
+};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    ast::{import_path, top_level_binding},
+    typst::TypstCore,
+};
+
+use super::{
+    diagnostics::{TypstCoreDiagnosticKind, TypstCoreDiagnostics},
+    edit::{TypstCoreFileEdit, TypstCoreTextEdit},
+    range::MonacoRange,
+};
+
+/// Mirrors the kinds of `monaco.languages.CodeAction`.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TypstCoreCodeActionKind {
+    QuickFix = "quickfix",
+    RefactorRewrite = "refactor.rewrite",
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreCodeAction {
+    pub title: String,
+    pub kind: TypstCoreCodeActionKind,
+    /// The message of the diagnostic the action fixes.
+    pub diagnostic: Option<String>,
+    /// Paths of empty source files to create before applying the edits.
+    pub create_files: Vec<String>,
+    pub edits: Vec<TypstCoreFileEdit>,
+    pub is_preferred: bool,
+}
+
+impl TypstCoreCodeAction {
+    fn quick_fix(title: String, diagnostic: &TypstCoreDiagnostics) -> Self {
+        Self {
+            title,
+            kind: TypstCoreCodeActionKind::QuickFix,
+            diagnostic: Some(diagnostic.message.clone()),
+            create_files: Vec::new(),
+            edits: Vec::new(),
+            is_preferred: false,
+        }
+    }
+
+    fn edit(mut self, source: &Source, edits: Vec<(Range<usize>, String)>) -> Self {
+        self.edits.push(TypstCoreFileEdit {
+            path: source.id().vpath().as_rooted_path().to_string_lossy().to_string(),
+            edits: edits
+                .into_iter()
+                .map(|(range, new_text)| TypstCoreTextEdit {
+                    range: MonacoRange::from_typst_range(range, source),
+                    new_text,
+                })
+                .collect(),
+        });
+        self
+    }
+
+    /// Collects the quick fixes for the diagnostics in the range and the rewrites of the selected markup.
+    pub fn collect(
+        core: &TypstCore,
+        source: &Source,
+        range: Range<usize>,
+        diagnostics: &[TypstCoreDiagnostics],
+    ) -> Vec<Self> {
+        let mut actions = Vec::new();
+        for diagnostic in diagnostics {
+            let target = &diagnostic.range;
+            if target.package.is_some() || FileId::new(None, VirtualPath::new(&target.path)) != source.id() {
+                continue;
+            }
+            // The diagnostics come from the client and may be stale, so skip those that no longer fit the source.
+            let Some(span) = target.monaco_range.to_typst_range(source) else {
+                continue;
+            };
+            if span.end < range.start || span.start > range.end {
+                continue;
+            }
+
+            if let Some(name) = diagnostic.message.strip_prefix("unknown variable: ") {
+                actions.extend(Self::import_binding(core, source, name, diagnostic));
+                actions.extend(Self::fix_misspelling(core, source, span.clone(), name, diagnostic));
+            } else if diagnostic.kind == Some(TypstCoreDiagnosticKind::FileNotFound) {
+                actions.extend(Self::create_file(source, span.start, diagnostic));
+            }
+        }
+        actions.extend(Self::wrap_selection(source, range));
+        actions
+    }
+
+    /// Imports a binding of the same name from the other sources, one action per source defining it.
+    /// Extends the item list of an existing import of that source instead of adding a second one.
+    fn import_binding(core: &TypstCore, source: &Source, name: &str, diagnostic: &TypstCoreDiagnostics) -> Vec<Self> {
+        let mut ids = core
+            .sources
+            .borrow()
+            .iter()
+            .filter(|(id, _)| id.package().is_none() && **id != source.id())
+            .filter(|(_, file)| top_level_binding(&file.source(), name).is_some())
+            .map(|(id, _)| *id)
+            .collect::<Vec<_>>();
+        ids.sort_by_key(|id| id.vpath().clone());
+
+        let root = LinkedNode::new(source.root());
+        let imports = root.children().filter(|child| child.kind() == SyntaxKind::ModuleImport).collect::<Vec<_>>();
+
+        // New imports go below the existing imports at the top of the file.
+        let offset = root
+            .children()
+            .take_while(|child| {
+                child.kind().is_trivia() || matches!(child.kind(), SyntaxKind::Hash | SyntaxKind::ModuleImport)
+            })
+            .filter(|child| child.kind() == SyntaxKind::ModuleImport)
+            .last()
+            .and_then(|import| source.byte_to_line(import.range().end))
+            .and_then(|line| source.line_to_byte(line + 1))
+            .unwrap_or(0);
+        let newline = if offset == source.len_bytes() && !source.text().ends_with('\n') && offset > 0 {
+            "\n"
+        } else {
+            ""
+        };
+
+        ids.into_iter()
+            .filter_map(|id| {
+                let path = relative_path(source.id().vpath(), id.vpath());
+                let existing = imports
+                    .iter()
+                    .find(|import| import_path(import.get()).is_some_and(|imported| source.id().join(&imported) == id));
+                let edit = match existing {
+                    Some(import) => extend_import(import, name)?,
+                    None => (offset..offset, format!("{newline}#import {path:?}: {name}\n")),
+                };
+                Some(Self::quick_fix(format!("Import `{name}` from \"{path}\""), diagnostic).edit(source, vec![edit]))
+            })
+            .collect()
+    }
+
+    /// Replaces an unknown identifier with the closest names in the standard library.
+    fn fix_misspelling(
+        core: &TypstCore,
+        source: &Source,
+        span: Range<usize>,
+        name: &str,
+        diagnostic: &TypstCoreDiagnostics,
+    ) -> Vec<Self> {
+        let max_distance = (name.chars().count() / 3).max(1);
+        let mut candidates = core
+            .library()
+            .global
+            .scope()
+            .iter()
+            // Typos rarely hit the first letter, so names sharing it come first among equally close ones.
+            .map(|(candidate, _)| {
+                let first_differs = candidate.chars().next() != name.chars().next();
+                (edit_distance(name, candidate), first_differs, candidate.to_string())
+            })
+            .filter(|(distance, _, _)| *distance <= max_distance)
+            .collect::<Vec<_>>();
+        candidates.sort();
+
+        candidates
+            .into_iter()
+            .take(3)
+            .enumerate()
+            .map(|(i, (_, _, candidate))| Self {
+                is_preferred: i == 0,
+                ..Self::quick_fix(format!("Change to `{candidate}`"), diagnostic)
+                    .edit(source, vec![(span.clone(), candidate)])
+            })
+            .collect()
+    }
+
+    /// Creates the missing file of an `#include` or `#import` at the offset.
+    fn create_file(source: &Source, offset: usize, diagnostic: &TypstCoreDiagnostics) -> Option<Self> {
+        let root = LinkedNode::new(source.root());
+        let leaf = root.leaf_at(offset, Side::After)?;
+        let string = leaf.cast::<ast::Str>()?;
+        if !matches!(leaf.parent_kind(), Some(SyntaxKind::ModuleInclude | SyntaxKind::ModuleImport)) {
+            return None;
+        }
+
+        let path = string.get();
+        if path.starts_with('@') {
+            return None;
+        }
+        let id = source.id().join(&path);
+        Some(Self {
+            is_preferred: true,
+            create_files: vec![id.vpath().as_rooted_path().to_string_lossy().to_string()],
+            ..Self::quick_fix(format!("Create \"{path}\""), diagnostic)
+        })
+    }
+
+    /// Wraps a selection within a single piece of markup in strong, emphasis or math delimiters.
+    fn wrap_selection(source: &Source, range: Range<usize>) -> Vec<Self> {
+        if range.is_empty() {
+            return Vec::new();
+        }
+        let root = LinkedNode::new(source.root());
+        let enclosing = |offset, side| {
+            let mut node = root.leaf_at(offset, side);
+            while let Some(current) = node {
+                if matches!(current.kind(), SyntaxKind::Markup | SyntaxKind::Code | SyntaxKind::Math) {
+                    return Some(current);
+                }
+                node = current.parent().cloned();
+            }
+            None
+        };
+        let (Some(start), Some(end)) = (enclosing(range.start, Side::After), enclosing(range.end, Side::Before)) else {
+            return Vec::new();
+        };
+        if start.kind() != SyntaxKind::Markup || start.span() != end.span() {
+            return Vec::new();
+        }
+
+        [("strong", "*"), ("emphasis", "_"), ("math", "$")]
+            .into_iter()
+            .map(|(name, delimiter)| {
+                Self {
+                    title: format!("Wrap in {name}"),
+                    kind: TypstCoreCodeActionKind::RefactorRewrite,
+                    diagnostic: None,
+                    create_files: Vec::new(),
+                    edits: Vec::new(),
+                    is_preferred: false,
+                }
+                .edit(
+                    source,
+                    vec![
+                        (range.start..range.start, delimiter.to_string()),
+                        (range.end..range.end, delimiter.to_string()),
+                    ],
+                )
+            })
+            .collect()
+    }
+}
+
+/// The edit adding a name to the items of an import, `None` if it already imports everything with `*`.
+fn extend_import(import: &LinkedNode, name: &str) -> Option<(Range<usize>, String)> {
+    let Some(items) = import.children().find(|child| child.kind() == SyntaxKind::ImportItems) else {
+        if import.children().any(|child| child.kind() == SyntaxKind::Star) {
+            return None;
+        }
+        let end = import.range().end;
+        return Some((end..end, format!(": {name}")));
+    };
+
+    let edit = match items.children().rfind(|child| !child.kind().is_trivia()) {
+        Some(last) if last.kind() == SyntaxKind::Comma => (last.range().end..last.range().end, format!(" {name}")),
+        Some(last) => (last.range().end..last.range().end, format!(", {name}")),
+        None => (items.offset()..items.offset(), name.to_string()),
+    };
+    Some(edit)
+}
+
+/// The path of `to` relative to the directory of `from`, as written in an import.
+fn relative_path(from: &VirtualPath, to: &VirtualPath) -> String {
+    let from = from.as_rootless_path().parent().map(|dir| dir.components().collect::<Vec<_>>()).unwrap_or_default();
+    let to = to.as_rootless_path().components().collect::<Vec<_>>();
+    let common = from.iter().zip(&to).take_while(|(a, b)| a == b).count();
+
+    let mut parts = vec![".."; from.len() - common];
+    parts.extend(to[common..].iter().filter_map(|component| match component {
+        Component::Normal(part) => part.to_str(),
+        _ => None,
+    }));
+    parts.join("/")
+}
+
+/// The edit distance between two strings, counting swapped neighbours as a single edit.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let (a, b) = (a.chars().collect::<Vec<_>>(), b.chars().collect::<Vec<_>>());
+    let mut rows = vec![(0..=b.len()).collect::<Vec<_>>()];
+    for i in 1..=a.len() {
+        let mut row = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let above = &rows[i - 1];
+            row[j] = (above[j - 1] + usize::from(a[i - 1] != b[j - 1])).min(above[j] + 1).min(row[j - 1] + 1);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                row[j] = row[j].min(rows[i - 2][j - 2] + 1);
+            }
+        }
+        rows.push(row);
+    }
+    rows[a.len()][b.len()]
+}
@@ -0,0 +1,148 @@
+use typst::syntax::Source;
+use typst_ide::{Completion, CompletionKind};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use super::range::MonacoRange;
+
+/// Mirrors `monaco.languages.CompletionItemKind`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MonacoCompletionItemKind {
+    Method = 0,
+    Function = 1,
+    Constructor = 2,
+    Field = 3,
+    Variable = 4,
+    Class = 5,
+    Struct = 6,
+    Interface = 7,
+    Module = 8,
+    Property = 9,
+    Event = 10,
+    Operator = 11,
+    Unit = 12,
+    Value = 13,
+    Constant = 14,
+    Enum = 15,
+    EnumMember = 16,
+    Keyword = 17,
+    Text = 18,
+    Color = 19,
+    File = 20,
+    Reference = 21,
+    Customcolor = 22,
+    Folder = 23,
+    TypeParameter = 24,
+    User = 25,
+    Issue = 26,
+    Snippet = 27,
+}
+
+impl From<&CompletionKind> for MonacoCompletionItemKind {
+    fn from(kind: &CompletionKind) -> Self {
+        match kind {
+            CompletionKind::Syntax => Self::Snippet,
+            CompletionKind::Func => Self::Function,
+            CompletionKind::Type => Self::Class,
+            CompletionKind::Param => Self::Property,
+            CompletionKind::Constant => Self::Constant,
+            CompletionKind::Path => Self::File,
+            CompletionKind::Package => Self::Module,
+            CompletionKind::Label => Self::Reference,
+            CompletionKind::Font => Self::Value,
+            CompletionKind::Symbol(_) => Self::Text,
+        }
+    }
+}
+
+/// Converts Typst's `${}` and `${name}` placeholders to Monaco's numbered `${1}` and `${1:name}`,
+/// escaping everything else Monaco would read as snippet syntax.
+pub fn to_monaco_snippet(apply: &str) -> String {
+    let mut snippet = String::with_capacity(apply.len());
+    let mut tabstop = 0;
+    let mut chars = apply.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '$' if chars.peek() == Some(&'{') => {
+                chars.next();
+                let mut placeholder = String::new();
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        break;
+                    }
+                    placeholder.push(c);
+                }
+
+                tabstop += 1;
+                if placeholder.is_empty() {
+                    snippet.push_str(&format!("${{{}}}", tabstop));
+                } else {
+                    let placeholder = placeholder
+                        .replace('\\', "\\\\")
+                        .replace('$', "\\$")
+                        .replace('}', "\\}");
+                    snippet.push_str(&format!("${{{}:{}}}", tabstop, placeholder));
+                }
+            }
+            '$' | '\\' => {
+                snippet.push('\\');
+                snippet.push(c);
+            }
+            _ => snippet.push(c),
+        }
+    }
+
+    snippet
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreCompletion {
+    pub label: String,
+    pub kind: MonacoCompletionItemKind,
+    /// The text to insert, a Monaco snippet if `is_snippet` is set.
+    pub insert_text: String,
+    pub is_snippet: bool,
+    /// A short description, for symbols the symbol itself.
+    pub detail: Option<String>,
+    /// The range replaced by the completion.
+    pub range: MonacoRange,
+}
+
+impl TypstCoreCompletion {
+    pub fn new(completion: Completion, range: MonacoRange) -> Self {
+        let (insert_text, is_snippet) = match &completion.apply {
+            Some(apply) if apply.contains("${") => (to_monaco_snippet(apply), true),
+            Some(apply) => (apply.to_string(), false),
+            None => (completion.label.to_string(), false),
+        };
+
+        let detail = match completion.kind {
+            CompletionKind::Symbol(c) => Some(c.to_string()),
+            _ => completion.detail.map(|detail| detail.to_string()),
+        };
+
+        Self {
+            kind: MonacoCompletionItemKind::from(&completion.kind),
+            label: completion.label.to_string(),
+            insert_text,
+            is_snippet,
+            detail,
+            range,
+        }
+    }
+
+    /// Converts the result of `typst_ide::autocomplete` for a cursor in the given source.
+    pub fn from_completions(
+        (start, completions): (usize, Vec<Completion>),
+        cursor: usize,
+        source: &Source,
+    ) -> Vec<Self> {
+        let range = MonacoRange::from_typst_range(start..cursor, source);
+        completions
+            .into_iter()
+            .map(|completion| Self::new(completion, range.clone()))
+            .collect()
+    }
+}
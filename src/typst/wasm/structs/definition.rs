@@ -8,43 +8,46 @@ use wasm_bindgen::prelude::wasm_bindgen;
 use crate::{
     ast::parse_let_binding,
     console_log,
-    typst::{source_file::SourceFile, TypstCore},
+    typst::{tidy::{TidyDocs, TidyParam}, TypstCore},
 };
 
 use super::{
     error::TypstCoreError,
-    range::{MonacoRange, TypstCoreRange},
+    hover::tooltip_markdown,
+    range::TypstCoreRange,
 };
 
-fn parse_cast_info(cast: &CastInfo) -> String {
+pub fn parse_cast_info(cast: &CastInfo) -> String {
     match cast {
         CastInfo::Any => "any".to_string(),
         CastInfo::Type(kind) => kind.short_name().to_string(),
-        CastInfo::Value(val, _desc) => val.name().map_or_else(|| "undef".to_string(), |n| n),
+        CastInfo::Value(val, _desc) => val.name().unwrap_or_else(|| "undef".to_string()),
         CastInfo::Union(types) => {
-            let types_str: Vec<String> = types.iter().map(|t| parse_cast_info(t)).collect();
+            let types_str: Vec<String> = types.iter().map(parse_cast_info).collect();
             format!("({})", types_str.join(" | "))
         }
     }
 }
 
+pub fn parse_param(p: &ParamInfo) -> String {
+    let variadic = if p.variadic { "..." } else { "" };
+    let name = p.name;
+    let ty = parse_cast_info(&p.input);
+    let default = if let Some(default) = p.default {
+        let val = default();
+        format!(" = {}", val.display().plain_text())
+    } else {
+        String::new()
+    };
+    let optional = if !p.required { "?" } else { "" };
+    let named = if p.named { ": " } else { "" };
+    format!("{}{}{}{}{}{}", variadic, name, optional, named, ty, default)
+}
+
 fn parse_params(params: &'static [ParamInfo]) -> String {
     params
         .iter()
-        .map(|p| {
-            let variadic = if p.variadic { "..." } else { "" };
-            let name = p.name;
-            let ty = parse_cast_info(&p.input);
-            let default = if p.default.is_some() {
-                let val = p.default.unwrap()();
-                format!(" = {}", val.display().plain_text())
-            } else {
-                String::new()
-            };
-            let optional = if !p.required { "?" } else { "" };
-            let named = if p.named { ": " } else { "" };
-            format!("{}{}{}{}{}{}", variadic, name, optional, named, ty, default)
-        })
+        .map(parse_param)
         .collect::<Vec<String>>()
         .join(", ")
 }
@@ -60,8 +63,8 @@ impl DefinitionExt for Value {
         match self {
             Value::Func(f) => {
                 let name = f.name().map(|n| n.to_string());
-                let params = f.params().map(|p| parse_params(p));
-                let return_type = f.returns().map(|r| parse_cast_info(r));
+                let params = f.params().map(parse_params);
+                let return_type = f.returns().map(parse_cast_info);
                 let mut name_str = String::new();
                 if let Some(name) = name {
                     name_str.push_str(&name);
@@ -95,18 +98,15 @@ impl DefinitionExt for Value {
 pub struct Args {
     pub name: String,
     pub docs: Option<String>,
+    pub types: Vec<String>,
 }
 
-impl From<(String, Vec<String>)> for Args {
-    fn from(value: (String, Vec<String>)) -> Self {
-        let docs = if value.1.is_empty() {
-            None
-        } else {
-            Some(value.1.join("\n"))
-        };
+impl From<TidyParam> for Args {
+    fn from(param: TidyParam) -> Self {
         Self {
-            name: value.0,
-            docs,
+            name: param.label,
+            docs: (!param.description.is_empty()).then_some(param.description),
+            types: param.types,
         }
     }
 }
@@ -120,6 +120,9 @@ pub struct TypstCoreDefinition {
     pub is_std: bool,
     pub is_fn: bool,
     pub args: Vec<Args>,
+    /// The types after `->` in the doc comment.
+    pub returns: Vec<String>,
+    pub examples: Vec<String>,
     pub def_span: Option<TypstCoreRange>,
     pub tooltip: Option<String>,
 }
@@ -129,44 +132,35 @@ impl TypstCoreDefinition {
         core: &TypstCore,
         typst_def: Option<Definition>,
         typst_tt: Option<Tooltip>,
-        source_file: &SourceFile,
     ) -> Result<Self, TypstCoreError> {
-        let (name, kind, docs, is_std, def_span, is_fn, args) = if let Some(def) = typst_def {
+        let (name, kind, docs, is_std, def_span, is_fn) = if let Some(def) = typst_def {
             match def {
                 Definition::Span(span) => {
-                    let output_raw = core.resolve_span(span, |node, id, source_file| {
+                    let output = core.resolve_span(span, |node, _, source_file| {
                         let def_span = TypstCoreRange::with_source(span, &source_file.source())?;
 
-                        let parsed =
-                            parse_let_binding(&node).expect("Definition should be a let binding!");
-
-                        let is_fn = node
-                            .parent_kind()
-                            .map(|parent_kind| parent_kind == SyntaxKind::Closure)
-                            .expect("Let binding identifier should have a parent.");
-
-                        Ok::<
-                            (
-                                TypstCoreRange,
-                                (String, Vec<String>),
-                                Vec<(String, Vec<String>)>,
-                                bool,
-                            ),
-                            TypstCoreError,
-                        >((def_span, parsed.0, parsed.1, is_fn))
+                        // Bindings that are not made by a let, e.g. imported names, only have their name.
+                        let (name, docs) = match parse_let_binding(&node) {
+                            Some((binding, params)) => {
+                                let docs = TidyDocs::from_binding(&binding, &params);
+                                (binding.0, docs)
+                            }
+                            None => (node.text().to_string(), TidyDocs::default()),
+                        };
+
+                        let is_fn = node.parent_kind() == Some(SyntaxKind::Closure);
+
+                        Ok::<(TypstCoreRange, String, TidyDocs, bool), TypstCoreError>((
+                            def_span, name, docs, is_fn,
+                        ))
                     });
-                    let output = output_raw
-                        .map_err(|e| TypstCoreError::from(e))?
-                        .expect("Node should be valid and found!")?;
-
-                    let (name, raw_docs) = output.1;
-                    let docs = if raw_docs.is_empty() {
-                        None
-                    } else {
-                        Some(raw_docs.join("\n"))
-                    };
-
-                    (Some(name), None, docs, false, Some(output.0), output.3, output.2)
+
+                    match output.map_err(TypstCoreError::from)?.transpose()? {
+                        Some((def_span, name, docs, is_fn)) => {
+                            (Some(name), None, Some(docs), false, Some(def_span), is_fn)
+                        }
+                        None => (None, None, None, false, None, false),
+                    }
                 }
                 Definition::Std(value) => {
                     let name = value.name();
@@ -180,36 +174,25 @@ impl TypstCoreDefinition {
                         kind,
                         docs
                     );
-                    (name, kind, docs, is_std, None, false, Vec::new())
+                    (name, kind, docs.map(|docs| TidyDocs { description: docs, ..TidyDocs::default() }), is_std, None, false)
                 }
             }
         } else {
-            (None, None, None, false, None, false, Vec::new())
+            (None, None, None, false, None, false)
         };
+        let tidy = docs.unwrap_or_default();
 
         Ok(Self {
             name,
             kind,
-            docs,
+            docs: (!tidy.description.is_empty()).then_some(tidy.description),
             is_std,
             is_fn,
-            args: args
-                .into_iter()
-                .map(Args::from)
-                .collect(),
+            args: tidy.params.into_iter().map(Args::from).collect(),
+            returns: tidy.returns,
+            examples: tidy.examples,
             def_span,
-            tooltip: None,
+            tooltip: typst_tt.as_ref().map(tooltip_markdown),
         })
-        // };
-
-        /* Self {
-            name,
-            kind,
-            docs,
-            is_std,
-            def_path,
-            def_span,
-            tooltip: Some("None".to_string())
-        } */
     }
 }
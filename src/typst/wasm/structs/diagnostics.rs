@@ -1,12 +1,15 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, str::FromStr};
 
 use serde::{Deserialize, Serialize};
-use typst::{diag::SourceDiagnostic, syntax::FileId};
+use typst::{
+    diag::{FileError, PackageError, SourceDiagnostic},
+    syntax::{package::PackageSpec, FileId, Span},
+};
 use wasm_bindgen::prelude::wasm_bindgen;
 
-use crate::typst::source_file::SourceFile;
+use crate::{ast::collect_string_literals, typst::source_file::SourceFile};
 
-use super::{error::TypstCoreError, range::TypstCoreRange};
+use super::range::TypstCoreRange;
 
 
 #[wasm_bindgen]
@@ -26,29 +29,130 @@ impl From<typst::diag::Severity> for TypstCoreSeverity {
 }
 
 
+/// What went wrong for a diagnostic caused by a file or package that could not be loaded.
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TypstCoreDiagnosticKind {
+    FileNotFound = "file-not-found",
+    PackageNotFound = "package-not-found",
+    PackageVersionNotFound = "package-version-not-found",
+    /// The namespace has a registry, but the package has not been installed with `install_package` yet.
+    PackageNotInstalled = "package-not-installed",
+    NetworkFailed = "network-failed",
+    MalformedArchive = "malformed-archive",
+}
+
+impl TypstCoreDiagnosticKind {
+    /// The kind of a file error returned by the world for the given file.
+    pub fn from_file_error(id: FileId, error: &FileError) -> Option<Self> {
+        match error {
+            FileError::NotFound(_) => Some(Self::FileNotFound),
+            FileError::Package(PackageError::NotFound(_)) => Some(Self::PackageNotFound),
+            FileError::Package(PackageError::VersionNotFound(..)) => Some(Self::PackageVersionNotFound),
+            FileError::Package(PackageError::NetworkFailed(_)) => Some(Self::NetworkFailed),
+            FileError::Package(PackageError::MalformedArchive(_)) => Some(Self::MalformedArchive),
+            // The only other package error is the one for packages that are not installed yet.
+            FileError::Package(PackageError::Other(_)) if id.package().is_some() => Some(Self::PackageNotInstalled),
+            _ => None,
+        }
+    }
+
+    /// The kind of a diagnostic at the given span, if it names a path or package that failed to load.
+    ///
+    /// The compiler only keeps the message of a file error, so the failed file is found through the
+    /// string literals at the span instead, resolved like the compiler does. Paths computed at runtime are not
+    /// found this way.
+    fn at_span(span: Span, sources: &HashMap<FileId, SourceFile>, failures: &HashMap<FileId, FileError>) -> Option<Self> {
+        let id = span.id()?;
+        let source = sources.get(&id)?.source();
+        let node = source.find(span)?;
+
+        let mut paths = Vec::new();
+        collect_string_literals(node.get(), &mut paths);
+        paths.iter().find_map(|path| {
+            let (failed, error) = if path.starts_with('@') {
+                let spec = PackageSpec::from_str(path).ok()?;
+                failures.iter().find(|(failed, _)| failed.package() == Some(&spec))?
+            } else {
+                failures.get_key_value(&id.join(path))?
+            };
+            Self::from_file_error(*failed, error)
+        })
+    }
+}
+
+/// A point in the call stack leading to a diagnostic, e.g. a function call or show rule.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TypstCoreTracepoint {
+    pub message: String,
+    pub range: TypstCoreRange,
+}
+
 #[wasm_bindgen(getter_with_clone)]
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TypstCoreDiagnostics {
     pub severity: TypstCoreSeverity,
     pub message: String,
+    /// Where to show the diagnostic, always in a user file if the trace passes through one.
     pub range: TypstCoreRange,
+    /// Where the diagnostic actually occurred, if that differs from `range`, e.g. inside a package.
+    pub origin: Option<TypstCoreRange>,
+    pub hints: Vec<String>,
+    /// The trace from the innermost to the outermost call site.
+    pub trace: Vec<TypstCoreTracepoint>,
+    /// Set if the diagnostic is caused by a file or package that could not be loaded.
+    pub kind: Option<TypstCoreDiagnosticKind>,
 }
 
 impl TypstCoreDiagnostics {
+    /// Converts a diagnostic of the compiler, `failures` are the files the compile failed to load and why.
     pub fn from_diagnostics(
         err: SourceDiagnostic,
         sources: &HashMap<FileId, SourceFile>,
-    ) -> Result<Self, TypstCoreError> {
+        failures: &HashMap<FileId, FileError>,
+    ) -> Self {
         let severity = TypstCoreSeverity::from(err.severity);
 
         let message = err.message.to_string();
 
-        let range = TypstCoreRange::with_sources(err.span, sources)?;
+        let kind = TypstCoreDiagnosticKind::at_span(err.span, sources, failures);
+
+        let hints = err.hints.iter().map(|hint| hint.to_string()).collect();
 
-        Ok(Self {
+        let trace = err
+            .trace
+            .iter()
+            .map(|point| TypstCoreTracepoint {
+                message: point.v.to_string(),
+                range: TypstCoreRange::with_sources(point.span, sources),
+            })
+            .collect::<Vec<_>>();
+
+        let (range, origin) = match TypstCoreRange::resolve(err.span, sources) {
+            Some(range) if range.is_user_file() => (range, None),
+            resolved => {
+                let origin = resolved.unwrap_or_else(|| TypstCoreRange::with_sources(err.span, sources));
+                let call_site = err
+                    .trace
+                    .iter()
+                    .filter_map(|point| TypstCoreRange::resolve(point.span, sources))
+                    .find(TypstCoreRange::is_user_file);
+                match call_site {
+                    Some(call_site) => (call_site, Some(origin)),
+                    None => (origin, None),
+                }
+            }
+        };
+
+        Self {
             severity,
             message,
             range,
-        })
+            origin,
+            hints,
+            trace,
+            kind,
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,302 @@
+use std::collections::{HashMap, HashSet};
+
+use typst::syntax::{ast::{self, AstNode}, FileId, LinkedNode, Source, SyntaxKind};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    ast::{child_by_kind, collect_prev_nodes, extract_text, parse_let_binding},
+    typst::{
+        source_file::SourceFile,
+        tidy::{TidyDocs, TidyParam},
+    },
+};
+
+use super::range::TypstCoreRange;
+
+#[wasm_bindgen]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TypstCoreDocItemKind {
+    Function = "function",
+    Variable = "variable",
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreDocParam {
+    pub name: String,
+    /// The source text of the default value of a named parameter.
+    pub default: Option<String>,
+    pub types: Vec<String>,
+    pub description: Option<String>,
+    pub variadic: bool,
+}
+
+impl From<TidyParam> for TypstCoreDocParam {
+    fn from(param: TidyParam) -> Self {
+        Self {
+            name: param.name,
+            default: param.default,
+            types: param.types,
+            description: (!param.description.is_empty()).then_some(param.description),
+            variadic: param.variadic,
+        }
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreDocItem {
+    /// The name the item is exported under.
+    pub name: String,
+    pub kind: TypstCoreDocItemKind,
+    /// The signature of a function, e.g. `framed(stroke: 1pt, body) -> content`, or just the name of a variable.
+    pub signature: String,
+    pub description: Option<String>,
+    pub params: Vec<TypstCoreDocParam>,
+    /// The return types of a function or the types of a variable.
+    pub returns: Vec<String>,
+    pub examples: Vec<String>,
+    /// Where the item is defined, in another file for re-exports.
+    pub range: TypstCoreRange,
+}
+
+impl TypstCoreDocItem {
+    fn new(name: String, kind: TypstCoreDocItemKind, labels: &[String], docs: TidyDocs, range: TypstCoreRange) -> Self {
+        let mut signature = name.clone();
+        if kind == TypstCoreDocItemKind::Function {
+            signature.push_str(&format!("({})", labels.join(", ")));
+        }
+        if !docs.returns.is_empty() {
+            signature.push_str(&format!(" -> {}", docs.returns.join(" | ")));
+        }
+        Self {
+            name,
+            kind,
+            signature,
+            description: (!docs.description.is_empty()).then_some(docs.description),
+            params: docs.params.into_iter().map(TypstCoreDocParam::from).collect(),
+            returns: docs.returns,
+            examples: docs.examples,
+            range,
+        }
+    }
+
+    /// The items of a top-level let binding, a destructuring binding has one per name.
+    fn from_binding(node: &LinkedNode, source: &Source) -> Vec<Self> {
+        let Some(binding) = node.cast::<ast::LetBinding>() else {
+            return Vec::new();
+        };
+        let idents = binding.kind().bindings();
+        let range = |span| TypstCoreRange::with_source(span, source).unwrap_or_default();
+
+        match (parse_let_binding(node), idents.as_slice()) {
+            (Some((documented, params)), [ident]) => {
+                let kind = match child_by_kind(node, SyntaxKind::Closure) {
+                    Some(_) => TypstCoreDocItemKind::Function,
+                    None => TypstCoreDocItemKind::Variable,
+                };
+                let labels = params.iter().map(|(label, _)| label.clone()).collect::<Vec<_>>();
+                let docs = TidyDocs::from_binding(&documented, &params);
+                vec![Self::new(documented.0, kind, &labels, docs, range(ident.span()))]
+            }
+            _ => {
+                let docs = TidyDocs::parse(&extract_text(&collect_prev_nodes(node, &[SyntaxKind::LineComment], 1)));
+                idents
+                    .iter()
+                    .map(|ident| {
+                        let name = ident.get().to_string();
+                        Self::new(name, TypstCoreDocItemKind::Variable, &[], docs.clone(), range(ident.span()))
+                    })
+                    .collect()
+            }
+        }
+    }
+}
+
+/// The API documentation of a module, with a reference page rendered from it.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreModuleDocs {
+    pub path: String,
+    pub items: Vec<TypstCoreDocItem>,
+    /// The reference page as Typst markup, the descriptions are embedded as they are.
+    pub typst: String,
+    /// The reference page as a standalone HTML document.
+    pub html: String,
+}
+
+impl TypstCoreModuleDocs {
+    pub fn new(source: &Source, sources: &HashMap<FileId, SourceFile>) -> Self {
+        let items = module_items(source, sources, &mut HashSet::new())
+            .into_iter()
+            // Names starting with an underscore are private by convention.
+            .filter(|item| !item.name.starts_with('_'))
+            .collect::<Vec<_>>();
+        let path = source.id().vpath().as_rooted_path().to_string_lossy().to_string();
+        Self {
+            typst: render_typst(&path, &items),
+            html: render_html(&path, &items),
+            path,
+            items,
+        }
+    }
+}
+
+/// Collects the items bound at the top level of a source in order, including those imported from other user files.
+/// Later bindings shadow earlier ones of the same name, cyclic imports are skipped.
+fn module_items(source: &Source, sources: &HashMap<FileId, SourceFile>, visited: &mut HashSet<FileId>) -> Vec<TypstCoreDocItem> {
+    visited.insert(source.id());
+
+    let mut items: Vec<TypstCoreDocItem> = Vec::new();
+    for child in LinkedNode::new(source.root()).children() {
+        let found = match child.kind() {
+            SyntaxKind::LetBinding => TypstCoreDocItem::from_binding(&child, source),
+            SyntaxKind::ModuleImport => child
+                .cast::<ast::ModuleImport>()
+                .map(|import| reexported_items(import, source, sources, visited))
+                .unwrap_or_default(),
+            _ => Vec::new(),
+        };
+        for item in found {
+            items.retain(|existing| existing.name != item.name);
+            items.push(item);
+        }
+    }
+    visited.remove(&source.id());
+    items
+}
+
+/// The items an `#import` of another user file adds to the scope of the importing module.
+fn reexported_items(
+    import: ast::ModuleImport,
+    source: &Source,
+    sources: &HashMap<FileId, SourceFile>,
+    visited: &mut HashSet<FileId>,
+) -> Vec<TypstCoreDocItem> {
+    let (ast::Expr::Str(path), Some(imports)) = (import.source(), import.imports()) else {
+        return Vec::new();
+    };
+    let path = path.get();
+    let id = source.id().join(&path);
+    if path.starts_with('@') || visited.contains(&id) {
+        return Vec::new();
+    }
+    let Some(imported) = sources.get(&id).map(SourceFile::source) else {
+        return Vec::new();
+    };
+
+    let items = module_items(&imported, sources, visited);
+    match imports {
+        ast::Imports::Wildcard => items,
+        ast::Imports::Items(list) => list
+            .iter()
+            .filter(|item| item.path().iter().count() == 1)
+            .filter_map(|item| {
+                let original = item.original_name();
+                let found = items.iter().find(|candidate| candidate.name == original.as_str())?;
+                let name = item.bound_name().get().to_string();
+                Some(TypstCoreDocItem {
+                    signature: found.signature.replacen(found.name.as_str(), &name, 1),
+                    name,
+                    ..found.clone()
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Wraps code in a raw block fenced by more backticks than it contains in a row.
+fn raw_block(code: &str, lang: &str) -> String {
+    let longest = code.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{fence}{lang}\n{code}\n{fence}")
+}
+
+/// Formats text as inline raw, falling back to a `raw` call if backticks or line breaks would get in the way.
+fn raw_inline(text: &str) -> String {
+    if text.is_empty() || text.contains(['`', '\n']) {
+        format!("#raw({:?})", text)
+    } else {
+        format!("`{}`", text)
+    }
+}
+
+fn render_typst(path: &str, items: &[TypstCoreDocItem]) -> String {
+    let mut out = format!("= {}\n", raw_inline(path));
+    for item in items {
+        out.push_str(&format!("\n== {} <{}>\n", raw_inline(&item.name), item.name));
+        out.push_str(&raw_block(&item.signature, "typc"));
+        out.push('\n');
+        if let Some(description) = &item.description {
+            out.push_str(&format!("\n{}\n", description));
+        }
+
+        if !item.params.is_empty() {
+            out.push_str("\n*Parameters*\n");
+            for param in &item.params {
+                out.push_str(&format!("/ {}:", raw_inline(&param.name)));
+                if !param.types.is_empty() {
+                    let types = param.types.iter().map(|ty| raw_inline(ty)).collect::<Vec<_>>();
+                    out.push_str(&format!(" {}", types.join(" | ")));
+                }
+                if let Some(default) = &param.default {
+                    out.push_str(&format!(" (default: {})", raw_inline(default)));
+                }
+                if let Some(description) = &param.description {
+                    out.push_str(&format!(" \\ {}", description.replace('\n', " ")));
+                }
+                out.push('\n');
+            }
+        }
+
+        for example in &item.examples {
+            out.push_str(&format!("\n*Example*\n{}\n", raw_block(example, "typ")));
+        }
+    }
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn render_html(path: &str, items: &[TypstCoreDocItem]) -> String {
+    let mut body = format!("<h1><code>{}</code></h1>\n", escape_html(path));
+    for item in items {
+        let name = escape_html(&item.name);
+        body.push_str(&format!("<section id=\"{name}\">\n<h2><code>{name}</code></h2>\n"));
+        body.push_str(&format!("<pre><code>{}</code></pre>\n", escape_html(&item.signature)));
+        if let Some(description) = &item.description {
+            body.push_str(&format!("<p>{}</p>\n", escape_html(description)));
+        }
+
+        if !item.params.is_empty() {
+            body.push_str("<h3>Parameters</h3>\n<dl>\n");
+            for param in &item.params {
+                body.push_str(&format!("<dt><code>{}</code>", escape_html(&param.name)));
+                if !param.types.is_empty() {
+                    body.push_str(&format!(" <span class=\"types\">{}</span>", escape_html(&param.types.join(" | "))));
+                }
+                if let Some(default) = &param.default {
+                    body.push_str(&format!(" <span class=\"default\">= <code>{}</code></span>", escape_html(default)));
+                }
+                body.push_str("</dt>\n");
+                if let Some(description) = &param.description {
+                    body.push_str(&format!("<dd>{}</dd>\n", escape_html(description)));
+                }
+            }
+            body.push_str("</dl>\n");
+        }
+
+        for example in &item.examples {
+            body.push_str(&format!("<h3>Example</h3>\n<pre><code>{}</code></pre>\n", escape_html(example)));
+        }
+        body.push_str("</section>\n");
+    }
+
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>\n{}</body>\n</html>\n",
+        escape_html(path),
+        body
+    )
+}
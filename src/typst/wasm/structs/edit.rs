@@ -0,0 +1,39 @@
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use super::range::{MonacoRange, TypstCoreRange};
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreTextEdit {
+    pub range: MonacoRange,
+    pub new_text: String,
+}
+
+/// All edits to a single file, in the order they appear in the file.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreFileEdit {
+    pub path: String,
+    pub edits: Vec<TypstCoreTextEdit>,
+}
+
+impl TypstCoreFileEdit {
+    /// Groups ranges by file, replacing each of them with the same text.
+    pub fn replace_all(ranges: Vec<TypstCoreRange>, new_text: &str) -> Vec<Self> {
+        let mut files: Vec<Self> = Vec::new();
+        for range in ranges {
+            let edit = TypstCoreTextEdit {
+                range: range.monaco_range,
+                new_text: new_text.to_string(),
+            };
+            match files.iter_mut().find(|file| file.path == range.path) {
+                Some(file) => file.edits.push(edit),
+                None => files.push(Self {
+                    path: range.path,
+                    edits: vec![edit],
+                }),
+            }
+        }
+        files
+    }
+}
@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use tsify::Tsify;
-use typst::diag::FileError;
+use typst::diag::{FileError, PackageError};
 use wasm_bindgen::{convert::{FromWasmAbi, IntoWasmAbi}, JsValue};
 
 use super::diagnostics::TypstCoreDiagnostics;
@@ -21,43 +21,52 @@ macro_rules! typst_error {
 #[derive(Clone, Debug, Serialize, Deserialize, Tsify)]
 pub enum TypstCoreError {
     CompileError(Vec<TypstCoreDiagnostics>),
+    FileNotFound { path: String },
+    PackageNotFound { spec: String },
+    /// The package exists, but not in the requested version.
+    PackageVersionNotFound { spec: String, latest: String },
+    NetworkFailed { message: Option<String> },
+    MalformedArchive { message: Option<String> },
+    RootNotSet,
+    TargetNotFound { name: String },
+    InvalidPosition { path: String, line_number: usize, column: usize },
     DefaultError(String),
 }
 
+impl TypstCoreError {
+    pub fn file_not_found(path: impl Into<String>) -> Self {
+        TypstCoreError::FileNotFound { path: path.into() }
+    }
+}
+
 impl From<FileError> for TypstCoreError {
     fn from(value: FileError) -> Self {
         match value {
-            FileError::NotFound(path_buf) => TypstCoreError::DefaultError(format!(
-                "File not found: {}",
-                path_buf.display()
-            )),
+            FileError::NotFound(path_buf) => TypstCoreError::FileNotFound {
+                path: path_buf.to_string_lossy().to_string(),
+            },
             FileError::AccessDenied => TypstCoreError::DefaultError("FileError: Access denied".to_string()),
             FileError::IsDirectory => TypstCoreError::DefaultError("FileError: File a directory".to_string()),
             FileError::NotSource => TypstCoreError::DefaultError("FileError: Not a source file".to_string()),
             FileError::InvalidUtf8 => TypstCoreError::DefaultError("FileError: Content is invalid UTF-8".to_string()),
             FileError::Package(package_error) => {
                 match package_error {
-                    typst::diag::PackageError::NotFound(package_spec) => 
-                        TypstCoreError::DefaultError(format!(
-                            "FileError: Package not found: {}",
-                            package_spec
-                        )),
-                    typst::diag::PackageError::VersionNotFound(package_spec, package_version) => 
-                        TypstCoreError::DefaultError(format!(
-                            "FileError: Package version not found: {}@{}",
-                            package_spec, package_version
-                        )),
-                    typst::diag::PackageError::NetworkFailed(eco_string) => 
-                        TypstCoreError::DefaultError(format!(
-                            "FileError: Network failed: {:?}",
-                            eco_string
-                        )),
-                    typst::diag::PackageError::MalformedArchive(eco_string) => 
-                        TypstCoreError::DefaultError(format!(
-                            "FileError: Malformed archive: {:?}",
-                            eco_string
-                        )),
-                    typst::diag::PackageError::Other(eco_string) => 
+                    PackageError::NotFound(package_spec) => TypstCoreError::PackageNotFound {
+                        spec: package_spec.to_string(),
+                    },
+                    PackageError::VersionNotFound(package_spec, latest) => {
+                        TypstCoreError::PackageVersionNotFound {
+                            spec: package_spec.to_string(),
+                            latest: latest.to_string(),
+                        }
+                    }
+                    PackageError::NetworkFailed(eco_string) => TypstCoreError::NetworkFailed {
+                        message: eco_string.map(|s| s.to_string()),
+                    },
+                    PackageError::MalformedArchive(eco_string) => TypstCoreError::MalformedArchive {
+                        message: eco_string.map(|s| s.to_string()),
+                    },
+                    PackageError::Other(eco_string) => 
                         TypstCoreError::DefaultError(format!(
                             "FileError: Other package error: {:?}",
                             eco_string
@@ -73,7 +82,7 @@ impl From<FileError> for TypstCoreError {
 }
 
 
-/// Wasm Bindgen
+// Wasm Bindgen
 
 impl From<TypstCoreError> for JsValue {
     fn from(val: TypstCoreError) -> Self {
@@ -0,0 +1,122 @@
+use typst::syntax::{ast, LinkedNode, Side, Source, SyntaxKind};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use super::range::MonacoRange;
+
+/// Mirrors `monaco.languages.FoldingRangeKind`.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TypstCoreFoldingRangeKind {
+    Comment,
+    Imports,
+    Region,
+}
+
+/// A foldable range of one-indexed lines, the start line stays visible when folded.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TypstCoreFoldingRange {
+    pub start: usize,
+    pub end: usize,
+    pub kind: Option<TypstCoreFoldingRangeKind>,
+}
+
+impl TypstCoreFoldingRange {
+    /// Collects the folding ranges of blocks, multi-line calls, raw blocks, block comments and heading sections.
+    pub fn from_source(source: &Source) -> Vec<Self> {
+        let mut ranges = Vec::new();
+        Self::collect(source, &LinkedNode::new(source.root()), &mut ranges);
+        ranges.sort_by_key(|range| (range.start, std::cmp::Reverse(range.end)));
+        ranges.dedup_by_key(|range| (range.start, range.end));
+        ranges
+    }
+
+    fn line(source: &Source, offset: usize) -> usize {
+        source.byte_to_line(offset).unwrap_or_default() + 1
+    }
+
+    fn push(ranges: &mut Vec<Self>, start: usize, end: usize, kind: Option<TypstCoreFoldingRangeKind>) {
+        if end > start {
+            ranges.push(Self { start, end, kind });
+        }
+    }
+
+    fn collect(source: &Source, node: &LinkedNode, ranges: &mut Vec<Self>) {
+        let start = Self::line(source, node.offset());
+        let end = Self::line(source, node.range().end);
+        match node.kind() {
+            // The closing delimiter stays visible.
+            SyntaxKind::CodeBlock
+            | SyntaxKind::ContentBlock
+            | SyntaxKind::Args
+            | SyntaxKind::Array
+            | SyntaxKind::Dict
+            | SyntaxKind::Params
+            | SyntaxKind::Raw => Self::push(ranges, start, end - 1, Some(TypstCoreFoldingRangeKind::Region)),
+            SyntaxKind::BlockComment => Self::push(ranges, start, end, Some(TypstCoreFoldingRangeKind::Comment)),
+            SyntaxKind::Markup => Self::heading_sections(source, node, ranges),
+            _ => {}
+        }
+
+        for child in node.children() {
+            Self::collect(source, &child, ranges);
+        }
+    }
+
+    /// Folds each heading up to the next heading of the same or a lower level in the same markup.
+    fn heading_sections(source: &Source, markup: &LinkedNode, ranges: &mut Vec<Self>) {
+        let children = markup.children().collect::<Vec<_>>();
+        for (i, child) in children.iter().enumerate() {
+            let Some(heading) = child.cast::<ast::Heading>() else {
+                continue;
+            };
+            let depth = heading.depth();
+
+            let section = children[i + 1..]
+                .iter()
+                .take_while(|next| next.cast::<ast::Heading>().is_none_or(|next| next.depth() > depth))
+                .filter(|next| !matches!(next.kind(), SyntaxKind::Space | SyntaxKind::Parbreak))
+                .last();
+
+            if let Some(last) = section {
+                Self::push(
+                    ranges,
+                    Self::line(source, child.offset()),
+                    Self::line(source, last.range().end),
+                    None,
+                );
+            }
+        }
+    }
+}
+
+/// The ranges to select when expanding the selection from a position, from the innermost to the outermost.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, Default)]
+pub struct TypstCoreSelectionRange {
+    pub ranges: Vec<MonacoRange>,
+}
+
+impl TypstCoreSelectionRange {
+    pub fn new(source: &Source, cursor: usize) -> Self {
+        let root = LinkedNode::new(source.root());
+        let leaf = root
+            .leaf_at(cursor, Side::After)
+            .filter(|leaf| !leaf.kind().is_trivia())
+            .or_else(|| root.leaf_at(cursor, Side::Before));
+
+        let mut ranges: Vec<MonacoRange> = Vec::new();
+        let mut previous = None;
+        let mut node = leaf;
+        while let Some(current) = node {
+            let range = current.range();
+            // Parents often cover exactly the same text, e.g. an identifier and the expression it forms.
+            if previous != Some(range.clone()) {
+                ranges.push(MonacoRange::from_typst_range(range.clone(), source));
+                previous = Some(range);
+            }
+            node = current.parent().cloned();
+        }
+        Self { ranges }
+    }
+}
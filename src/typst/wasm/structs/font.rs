@@ -0,0 +1,199 @@
+use std::{collections::HashMap, ops::Range};
+
+use serde::{Deserialize, Serialize};
+use typst::{
+    layout::{Frame, FrameItem, PagedDocument},
+    syntax::{FileId, LinkedNode, SyntaxKind},
+    text::{FontBook, FontFlags, FontInfo, FontStyle},
+};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::typst::source_file::SourceFile;
+
+/// Representative characters used to hint which scripts a font family covers.
+const COVERAGE_HINTS: &[(&str, char)] = &[
+    ("latin", 'a'),
+    ("greek", 'α'),
+    ("cyrillic", 'я'),
+    ("hebrew", 'א'),
+    ("arabic", 'ب'),
+    ("devanagari", 'क'),
+    ("cjk", '中'),
+    ("hangul", '한'),
+    ("math", '∑'),
+    ("emoji", '😀'),
+];
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TypstCoreFontInfo {
+    pub family: String,
+    /// One of `normal`, `italic` or `oblique`.
+    pub style: String,
+    /// The weight between 100 and 900, 400 being regular.
+    pub weight: u16,
+    /// The stretch as a ratio, 1.0 being normal width.
+    pub stretch: f64,
+    /// Whether the font is bundled with the core rather than user supplied.
+    pub builtin: bool,
+}
+
+impl TypstCoreFontInfo {
+    pub fn new(info: &FontInfo, builtin: bool) -> Self {
+        let style = match info.variant.style {
+            FontStyle::Normal => "normal",
+            FontStyle::Italic => "italic",
+            FontStyle::Oblique => "oblique",
+        };
+
+        TypstCoreFontInfo {
+            family: info.family.clone(),
+            style: style.to_string(),
+            weight: info.variant.weight.to_number(),
+            stretch: info.variant.stretch.to_ratio().get(),
+            builtin,
+        }
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TypstCoreFontFamily {
+    pub family: String,
+    pub variants: Vec<TypstCoreFontInfo>,
+    /// The scripts covered by at least one variant, e.g. `latin` or `cjk`.
+    pub coverage: Vec<String>,
+    pub monospace: bool,
+    pub serif: bool,
+}
+
+impl TypstCoreFontFamily {
+    /// Groups the fonts of a book by family, fonts with an index below `builtin_fonts` are bundled.
+    pub fn from_book(book: &FontBook, builtin_fonts: usize) -> Vec<Self> {
+        let mut families: Vec<Self> = Vec::new();
+        let mut index = 0;
+        while let Some(info) = book.info(index) {
+            let variant = TypstCoreFontInfo::new(info, index < builtin_fonts);
+            let family = match families.iter_mut().find(|f| f.family == info.family) {
+                Some(family) => family,
+                None => {
+                    families.push(Self {
+                        family: info.family.clone(),
+                        variants: Vec::new(),
+                        coverage: Vec::new(),
+                        monospace: false,
+                        serif: false,
+                    });
+                    families.last_mut().unwrap()
+                }
+            };
+
+            for (script, c) in COVERAGE_HINTS {
+                if info.coverage.contains(*c as u32) && !family.coverage.iter().any(|s| s == script) {
+                    family.coverage.push(script.to_string());
+                }
+            }
+            family.monospace |= info.flags.contains(FontFlags::MONOSPACE);
+            family.serif |= info.flags.contains(FontFlags::SERIF);
+            family.variants.push(variant);
+            index += 1;
+        }
+
+        families.sort_by(|a, b| a.family.cmp(&b.family));
+        families
+    }
+}
+
+/// A requested font family that is not in the font book, with the families the layout used instead.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TypstCoreMissingFont {
+    /// The family as requested, in lowercase.
+    pub family: String,
+    /// The families of the laid out text styled by the `set text` rule or `text` call requesting the family, sorted.
+    /// Empty if that text can't be told apart, e.g. when the family is requested in a show rule.
+    pub used: Vec<String>,
+}
+
+impl TypstCoreMissingFont {
+    /// Reads the family from a compiler warning about an unknown font family.
+    pub fn from_warning(message: &str) -> Option<Self> {
+        let family = message.strip_prefix("unknown font family: ")?;
+        Some(Self {
+            family: family.to_string(),
+            used: Vec::new(),
+        })
+    }
+
+    /// Adds the families of the text items within the range of the file.
+    pub fn add_used(&mut self, text_fonts: &[TextFont], id: FileId, range: Range<usize>) {
+        for (_, _, family) in text_fonts
+            .iter()
+            .filter(|(text_id, text_range, _)| *text_id == id && range.contains(&text_range.start))
+        {
+            if !self.used.contains(family) {
+                self.used.push(family.clone());
+            }
+        }
+        self.used.sort();
+    }
+}
+
+/// The file and byte range of a laid out text item together with the family of its font.
+pub type TextFont = (FileId, Range<usize>, String);
+
+/// Collects the text items of a document whose text comes from one of the sources.
+pub fn text_fonts(doc: &PagedDocument, sources: &HashMap<FileId, SourceFile>) -> Vec<TextFont> {
+    fn collect(frame: &Frame, sources: &HashMap<FileId, SourceFile>, fonts: &mut Vec<TextFont>) {
+        for (_, item) in frame.items() {
+            match item {
+                FrameItem::Group(group) => collect(&group.frame, sources, fonts),
+                FrameItem::Text(text) => {
+                    let Some((span, _)) = text.glyphs.first().map(|glyph| glyph.span) else {
+                        continue;
+                    };
+                    let Some(id) = span.id() else {
+                        continue;
+                    };
+                    if let Some(range) = sources.get(&id).and_then(|file| file.source.range(span)) {
+                        fonts.push((id, range, text.font.info().family.clone()));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let mut fonts = Vec::new();
+    for page in &doc.pages {
+        collect(&page.frame, sources, &mut fonts);
+    }
+    fonts
+}
+
+/// The range of the text styled by the `set text` rule or `text` call whose font argument is at the node:
+/// the rest of the enclosing markup or code for a set rule and the call itself for a call.
+pub fn styled_range(node: &LinkedNode) -> Option<Range<usize>> {
+    let args = node
+        .parent()
+        .filter(|named| named.kind() == SyntaxKind::Named)?
+        .parent()
+        .filter(|args| args.kind() == SyntaxKind::Args)?;
+    let rule = args.parent()?;
+    match rule.kind() {
+        SyntaxKind::FuncCall => Some(rule.range()),
+        SyntaxKind::SetRule => {
+            let scope = rule.parent().filter(|scope| matches!(scope.kind(), SyntaxKind::Markup | SyntaxKind::Code))?;
+            Some(rule.range().end..scope.range().end)
+        }
+        _ => None,
+    }
+}
+
+/// Which requested font families were missing during the last compile and which fonts were used instead.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct TypstCoreFontReport {
+    /// Families requested by the document that are not in the font book, sorted.
+    pub missing: Vec<TypstCoreMissingFont>,
+}
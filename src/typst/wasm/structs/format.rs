@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use wasm_bindgen::{convert::{FromWasmAbi, IntoWasmAbi}, JsValue};
+
+/// Settings for formatting sources.
+#[derive(Clone, Debug, Serialize, Deserialize, Tsify)]
+#[serde(default)]
+pub struct TypstCoreFormatOptions {
+    /// The number of spaces per indentation level.
+    pub indent_width: usize,
+    /// The width after which argument lists, arrays and dictionaries are broken into one item per line.
+    pub max_width: usize,
+    /// Whether to add a trailing comma after the last item of a broken list.
+    pub trailing_comma: bool,
+}
+
+impl Default for TypstCoreFormatOptions {
+    fn default() -> Self {
+        Self {
+            indent_width: 2,
+            max_width: 80,
+            trailing_comma: true,
+        }
+    }
+}
+
+// Wasm Bindgen
+
+impl From<TypstCoreFormatOptions> for JsValue {
+    fn from(val: TypstCoreFormatOptions) -> Self {
+        serde_wasm_bindgen::to_value(&val).unwrap()
+    }
+}
+
+impl wasm_bindgen::describe::WasmDescribe for TypstCoreFormatOptions {
+    fn describe() {
+        JsValue::describe()
+    }
+}
+
+impl wasm_bindgen::convert::IntoWasmAbi for TypstCoreFormatOptions {
+    type Abi = <JsValue as IntoWasmAbi>::Abi;
+
+    fn into_abi(self) -> Self::Abi {
+        serde_wasm_bindgen::to_value(&self).unwrap().into_abi()
+    }
+}
+
+impl wasm_bindgen::convert::FromWasmAbi for TypstCoreFormatOptions {
+    type Abi = <JsValue as FromWasmAbi>::Abi;
+
+    unsafe fn from_abi(js: Self::Abi) -> Self {
+        serde_wasm_bindgen::from_value(JsValue::from_abi(js)).unwrap()
+    }
+}
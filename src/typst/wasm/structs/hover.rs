@@ -0,0 +1,36 @@
+use typst_ide::Tooltip;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::typst::tidy::TidyDocs;
+
+use super::range::MonacoRange;
+
+/// Renders a tooltip as markdown, code is wrapped in a `typc` code block.
+pub fn tooltip_markdown(tooltip: &Tooltip) -> String {
+    match tooltip {
+        Tooltip::Text(text) => text.to_string(),
+        Tooltip::Code(code) => format!("```typc\n{}\n```", code),
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreHover {
+    /// The tooltip as markdown.
+    pub contents: String,
+    /// The range of the hovered node.
+    pub range: MonacoRange,
+}
+
+impl TypstCoreHover {
+    /// Shows the doc comment of the hovered binding above the tooltip.
+    pub fn new(tooltip: Option<&Tooltip>, docs: Option<&TidyDocs>, range: MonacoRange) -> Self {
+        let contents = docs
+            .map(TidyDocs::to_markdown)
+            .into_iter()
+            .chain(tooltip.map(tooltip_markdown))
+            .collect::<Vec<_>>()
+            .join("\n\n---\n\n");
+        Self { contents, range }
+    }
+}
@@ -0,0 +1,66 @@
+use std::collections::HashMap;
+
+use typst::{layout::Position, syntax::FileId};
+use typst_ide::Jump;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::typst::source_file::SourceFile;
+
+use super::range::{MonacoRange, TypstCoreRange};
+
+/// A point on a page of the document, in typographic points from the top left corner.
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TypstCorePagePosition {
+    /// The one-indexed page.
+    pub page: usize,
+    pub x: f64,
+    pub y: f64,
+}
+
+impl From<Position> for TypstCorePagePosition {
+    fn from(position: Position) -> Self {
+        Self {
+            page: position.page.get(),
+            x: position.point.x.to_pt(),
+            y: position.point.y.to_pt(),
+        }
+    }
+}
+
+/// Where a click in the preview leads to, exactly one of the fields is set.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, Default)]
+pub struct TypstCoreJump {
+    /// An empty range at the clicked position in the source.
+    pub source: Option<TypstCoreRange>,
+    /// The target of a clicked external link.
+    pub url: Option<String>,
+    /// The target of a clicked internal link.
+    pub position: Option<TypstCorePagePosition>,
+}
+
+impl TypstCoreJump {
+    pub fn new(jump: Jump, sources: &HashMap<FileId, SourceFile>) -> Option<Self> {
+        Some(match jump {
+            Jump::File(id, offset) => {
+                let source = sources.get(&id)?.source();
+                Self {
+                    source: Some(TypstCoreRange {
+                        monaco_range: MonacoRange::from_typst_range(offset..offset, &source),
+                        ..TypstCoreRange::in_file(id)
+                    }),
+                    ..Self::default()
+                }
+            }
+            Jump::Url(url) => Self {
+                url: Some(url.to_string()),
+                ..Self::default()
+            },
+            Jump::Position(position) => Self {
+                position: Some(position.into()),
+                ..Self::default()
+            },
+        })
+    }
+}
@@ -1,7 +1,21 @@
 pub mod error;
 pub mod diagnostics;
+pub mod font;
+pub mod hover;
 pub mod package;
 pub mod range;
+pub mod registry;
 pub mod output;
+pub mod pdf;
+pub mod png;
 pub mod completion;
-pub mod definition;
\ No newline at end of file
+pub mod definition;
+pub mod signature;
+pub mod edit;
+pub mod symbols;
+pub mod jump;
+pub mod format;
+pub mod semantic;
+pub mod folding;
+pub mod code_action;
+pub mod docs;
@@ -2,17 +2,56 @@ use serde::{Deserialize, Serialize};
 use tsify::Tsify;
 use wasm_bindgen::{convert::{FromWasmAbi, IntoWasmAbi}, prelude::wasm_bindgen, JsValue};
 
+use super::diagnostics::TypstCoreDiagnostics;
+
 #[wasm_bindgen]
 #[derive(Clone, Debug)]
 pub enum OutputFormat {
     Svg = "svg",
     Html = "html",
+    Pdf = "pdf",
+    Png = "png",
+    /// Like `Svg`, but only returns the pages that changed since the last `SvgIncremental` compile.
+    SvgIncremental = "svg_incremental",
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, Tsify)]
 pub enum Output {
     Svg(Vec<String>),
     Html(String),
+    /// Serialized as bytes, so JS receives a `Uint8Array` instead of one number per byte.
+    Pdf(#[serde(with = "serde_bytes")] #[tsify(type = "Uint8Array")] Vec<u8>),
+    /// One PNG per page, each serialized as a `Uint8Array`.
+    Png(#[serde(with = "png_pages")] #[tsify(type = "Uint8Array[]")] Vec<Vec<u8>>),
+    SvgIncremental(TypstCoreSvgDiff),
+}
+
+/// The output of a successful compile together with the warnings it raised.
+#[derive(Clone, Debug, Serialize, Deserialize, Tsify)]
+pub struct CompileOutput {
+    pub output: Output,
+    pub warnings: Vec<TypstCoreDiagnostics>,
+}
+
+impl CompileOutput {
+    pub fn new(output: Output, warnings: Vec<TypstCoreDiagnostics>) -> Self {
+        Self { output, warnings }
+    }
+}
+
+/// A rendered page together with its zero-based index in the document.
+#[derive(Clone, Debug, Serialize, Deserialize, Tsify)]
+pub struct TypstCoreSvgPage {
+    pub index: usize,
+    pub svg: String,
+}
+
+/// The pages that changed since the previous incremental compile.
+#[derive(Clone, Debug, Serialize, Deserialize, Tsify)]
+pub struct TypstCoreSvgDiff {
+    /// The total number of pages, pages beyond it should be dropped.
+    pub page_count: usize,
+    pub changed: Vec<TypstCoreSvgPage>,
 }
 
 #[wasm_bindgen]
@@ -32,6 +71,48 @@ impl Output {
             None
         }
     }
+
+    pub fn pdf(self) -> Option<Vec<u8>> {
+        if let Output::Pdf(pdf) = self {
+            Some(pdf)
+        } else {
+            None
+        }
+    }
+}
+
+impl Output {
+    // Nested byte vectors can't cross the wasm boundary directly, JS reads them from the serialized `Output`.
+    pub fn png(self) -> Option<Vec<Vec<u8>>> {
+        if let Output::Png(png) = self {
+            Some(png)
+        } else {
+            None
+        }
+    }
+
+    pub fn svg_diff(self) -> Option<TypstCoreSvgDiff> {
+        if let Output::SvgIncremental(diff) = self {
+            Some(diff)
+        } else {
+            None
+        }
+    }
+}
+
+/// Serializes every page as bytes, `serde_bytes` only handles a single byte buffer.
+mod png_pages {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use serde_bytes::{ByteBuf, Bytes};
+
+    pub fn serialize<S: Serializer>(pages: &[Vec<u8>], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(pages.iter().map(|page| Bytes::new(page)))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<Vec<u8>>, D::Error> {
+        let pages = Vec::<ByteBuf>::deserialize(deserializer)?;
+        Ok(pages.into_iter().map(ByteBuf::into_vec).collect())
+    }
 }
 
 impl From<Output> for JsValue {
@@ -60,4 +141,32 @@ impl wasm_bindgen::convert::FromWasmAbi for Output {
     unsafe fn from_abi(js: Self::Abi) -> Self {
         serde_wasm_bindgen::from_value(JsValue::from_abi(js)).unwrap()
     }
-}
\ No newline at end of file
+}
+
+impl From<CompileOutput> for JsValue {
+    fn from(val: CompileOutput) -> Self {
+        serde_wasm_bindgen::to_value(&val).unwrap()
+    }
+}
+
+impl wasm_bindgen::describe::WasmDescribe for CompileOutput {
+    fn describe() {
+        JsValue::describe()
+    }
+}
+
+impl wasm_bindgen::convert::IntoWasmAbi for CompileOutput {
+    type Abi = <JsValue as IntoWasmAbi>::Abi;
+
+    fn into_abi(self) -> Self::Abi {
+        serde_wasm_bindgen::to_value(&self).unwrap().into_abi()
+    }
+}
+
+impl wasm_bindgen::convert::FromWasmAbi for CompileOutput {
+    type Abi = <JsValue as FromWasmAbi>::Abi;
+
+    unsafe fn from_abi(js: Self::Abi) -> Self {
+        serde_wasm_bindgen::from_value(JsValue::from_abi(js)).unwrap()
+    }
+}
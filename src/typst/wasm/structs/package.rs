@@ -1,7 +1,13 @@
+use std::str::FromStr;
+
 use serde::{Deserialize, Serialize};
 use typst::syntax::package::PackageSpec;
 use wasm_bindgen::prelude::wasm_bindgen;
 
+use crate::typst_error;
+
+use super::error::TypstCoreError;
+
 
 #[wasm_bindgen(getter_with_clone)]
 #[derive(Clone, Debug, Serialize, Deserialize, Hash, PartialEq, Eq)]
@@ -11,6 +17,18 @@ pub struct TypstCorePackage {
     pub version: String,
 }
 
+#[wasm_bindgen]
+impl TypstCorePackage {
+    #[wasm_bindgen(constructor)]
+    pub fn new(namespace: String, name: String, version: String) -> Self {
+        TypstCorePackage {
+            name,
+            namespace,
+            version,
+        }
+    }
+}
+
 impl PartialEq<PackageSpec> for TypstCorePackage {
     fn eq(&self, other: &PackageSpec) -> bool {
         self.name == other.name
@@ -35,4 +53,13 @@ impl PartialEq<TypstCorePackage> for PackageSpec {
             && self.namespace == other.namespace
             && format!("{:?}", self.version) == other.version
     }
-}
\ No newline at end of file
+}
+
+impl TryFrom<&TypstCorePackage> for PackageSpec {
+    type Error = TypstCoreError;
+
+    fn try_from(val: &TypstCorePackage) -> Result<Self, Self::Error> {
+        PackageSpec::from_str(&format!("@{}/{}:{}", val.namespace, val.name, val.version))
+            .map_err(|e| typst_error!(format!("Invalid package specification: {}", e)))
+    }
+}
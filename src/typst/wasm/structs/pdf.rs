@@ -0,0 +1,129 @@
+use std::num::NonZeroUsize;
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use typst::{foundations::Smart, layout::PageRanges, World};
+use typst_pdf::{PdfOptions, PdfStandard, PdfStandards, Timestamp};
+use wasm_bindgen::{convert::{FromWasmAbi, IntoWasmAbi}, JsValue};
+
+use crate::typst_error;
+
+use super::error::TypstCoreError;
+
+/// The PDF standards Typst can enforce conformance with.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize, Tsify)]
+pub enum TypstCorePdfStandard {
+    #[serde(rename = "1.7")]
+    V1_7,
+    #[serde(rename = "a-2b")]
+    A2b,
+    #[serde(rename = "a-3b")]
+    A3b,
+}
+
+impl From<TypstCorePdfStandard> for PdfStandard {
+    fn from(standard: TypstCorePdfStandard) -> Self {
+        match standard {
+            TypstCorePdfStandard::V1_7 => PdfStandard::V_1_7,
+            TypstCorePdfStandard::A2b => PdfStandard::A_2b,
+            TypstCorePdfStandard::A3b => PdfStandard::A_3b,
+        }
+    }
+}
+
+/// An inclusive, one-indexed range of pages. A missing bound leaves that side open.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Tsify)]
+pub struct TypstCorePageRange {
+    pub start: Option<usize>,
+    pub end: Option<usize>,
+}
+
+impl TypstCorePageRange {
+    pub fn to_page_ranges(ranges: &[TypstCorePageRange]) -> PageRanges {
+        PageRanges::new(
+            ranges
+                .iter()
+                .map(|range| range.start.and_then(NonZeroUsize::new)..=range.end.and_then(NonZeroUsize::new))
+                .collect(),
+        )
+    }
+}
+
+/// Settings for PDF export, mirroring `typst_pdf::PdfOptions`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize, Tsify)]
+#[serde(default)]
+pub struct TypstCorePdfOptions {
+    /// A stable identifier of the document, used to derive the PDF document id.
+    pub ident: Option<String>,
+    /// Whether to stamp the document with the current date from `World::today`.
+    pub timestamp: bool,
+    /// The PDF standards to enforce conformance with.
+    pub standards: Vec<TypstCorePdfStandard>,
+    /// The pages to export. Exports all pages when not set.
+    pub page_ranges: Option<Vec<TypstCorePageRange>>,
+}
+
+impl TypstCorePdfOptions {
+    pub fn to_typst(&self, world: &dyn World) -> Result<PdfOptions<'_>, TypstCoreError> {
+        let standards = self
+            .standards
+            .iter()
+            .copied()
+            .map(PdfStandard::from)
+            .collect::<Vec<_>>();
+        let standards = PdfStandards::new(&standards)
+            .map_err(|e| typst_error!(format!("Invalid PDF standards: {}", e)))?;
+
+        let timestamp = if self.timestamp {
+            let offset = Local::now().offset().local_minus_utc() / 60;
+            world
+                .today(None)
+                .and_then(|datetime| Timestamp::new_local(datetime, offset))
+        } else {
+            None
+        };
+
+        let page_ranges = self
+            .page_ranges
+            .as_deref()
+            .map(TypstCorePageRange::to_page_ranges);
+
+        Ok(PdfOptions {
+            ident: self.ident.as_deref().map_or(Smart::Auto, Smart::Custom),
+            timestamp,
+            page_ranges,
+            standards,
+        })
+    }
+}
+
+// Wasm Bindgen
+
+impl From<TypstCorePdfOptions> for JsValue {
+    fn from(val: TypstCorePdfOptions) -> Self {
+        serde_wasm_bindgen::to_value(&val).unwrap()
+    }
+}
+
+impl wasm_bindgen::describe::WasmDescribe for TypstCorePdfOptions {
+    fn describe() {
+        JsValue::describe()
+    }
+}
+
+impl wasm_bindgen::convert::IntoWasmAbi for TypstCorePdfOptions {
+    type Abi = <JsValue as IntoWasmAbi>::Abi;
+
+    fn into_abi(self) -> Self::Abi {
+        serde_wasm_bindgen::to_value(&self).unwrap().into_abi()
+    }
+}
+
+impl wasm_bindgen::convert::FromWasmAbi for TypstCorePdfOptions {
+    type Abi = <JsValue as FromWasmAbi>::Abi;
+
+    unsafe fn from_abi(js: Self::Abi) -> Self {
+        serde_wasm_bindgen::from_value(JsValue::from_abi(js)).unwrap()
+    }
+}
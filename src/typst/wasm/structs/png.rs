@@ -0,0 +1,120 @@
+use std::str::FromStr;
+
+use serde::{Deserialize, Serialize};
+use tsify::Tsify;
+use typst::{
+    foundations::Smart,
+    layout::{Page, PagedDocument},
+    visualize::{Color, Paint},
+};
+use wasm_bindgen::{convert::{FromWasmAbi, IntoWasmAbi}, JsValue};
+
+use crate::typst_error;
+
+use super::{error::TypstCoreError, pdf::TypstCorePageRange};
+
+/// Settings for PNG export.
+#[derive(Clone, Debug, Serialize, Deserialize, Tsify)]
+#[serde(default)]
+pub struct TypstCorePngOptions {
+    /// The pixel density in pixels per inch.
+    pub ppi: f32,
+    /// A hex color like `#ffffff` or `#00000000` that overrides the page fill.
+    pub background: Option<String>,
+    /// The pages to render. Renders all pages when not set.
+    pub pages: Option<Vec<TypstCorePageRange>>,
+}
+
+impl Default for TypstCorePngOptions {
+    fn default() -> Self {
+        Self {
+            ppi: 144.0,
+            background: None,
+            pages: None,
+        }
+    }
+}
+
+impl TypstCorePngOptions {
+    /// Renders every selected page of the document to PNG bytes.
+    pub fn render(&self, doc: &PagedDocument) -> Result<Vec<Vec<u8>>, TypstCoreError> {
+        let pages = self.pages.as_deref().map(TypstCorePageRange::to_page_ranges);
+
+        doc.pages
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| pages.as_ref().is_none_or(|pages| pages.includes_page_index(*i)))
+            .map(|(_, page)| self.render_page(page, self.ppi))
+            .collect()
+    }
+
+    /// Renders a single page at the given pixel density to PNG bytes.
+    pub fn render_page(&self, page: &Page, ppi: f32) -> Result<Vec<u8>, TypstCoreError> {
+        check_pixmap_size(page, ppi)?;
+        let pixmap = match &self.background {
+            Some(background) => {
+                let color = Color::from_str(background)
+                    .map_err(|e| typst_error!(format!("Invalid background color {:?}: {}", background, e)))?;
+                let mut page = page.clone();
+                page.fill = Smart::Custom(Some(Paint::Solid(color)));
+                typst_render::render(&page, ppi / 72.0)
+            }
+            None => typst_render::render(page, ppi / 72.0),
+        };
+
+        pixmap
+            .encode_png()
+            .map_err(|e| typst_error!(format!("Failed to encode PNG: {}", e)))
+    }
+}
+
+/// The largest pixmap in bytes, the renderer cannot allocate more than `i32::MAX` bytes per image.
+const MAX_PIXMAP_BYTES: f64 = i32::MAX as f64;
+
+/// Rejects a pixel density the page cannot be rendered at, as the renderer panics on those instead.
+fn check_pixmap_size(page: &Page, ppi: f32) -> Result<(), TypstCoreError> {
+    if !ppi.is_finite() || ppi <= 0.0 {
+        return Err(typst_error!(format!("Invalid pixel density {}, expected a positive number", ppi)));
+    }
+    let size = page.frame.size();
+    let pixels = |length: f64| (length * ppi as f64 / 72.0).round().max(1.0);
+    if pixels(size.x.to_pt()) * pixels(size.y.to_pt()) * 4.0 > MAX_PIXMAP_BYTES {
+        return Err(typst_error!(format!(
+            "A pixel density of {} is too high for a page of {:.0}x{:.0}pt",
+            ppi,
+            size.x.to_pt(),
+            size.y.to_pt()
+        )));
+    }
+    Ok(())
+}
+
+// Wasm Bindgen
+
+impl From<TypstCorePngOptions> for JsValue {
+    fn from(val: TypstCorePngOptions) -> Self {
+        serde_wasm_bindgen::to_value(&val).unwrap()
+    }
+}
+
+impl wasm_bindgen::describe::WasmDescribe for TypstCorePngOptions {
+    fn describe() {
+        JsValue::describe()
+    }
+}
+
+impl wasm_bindgen::convert::IntoWasmAbi for TypstCorePngOptions {
+    type Abi = <JsValue as IntoWasmAbi>::Abi;
+
+    fn into_abi(self) -> Self::Abi {
+        serde_wasm_bindgen::to_value(&self).unwrap().into_abi()
+    }
+}
+
+impl wasm_bindgen::convert::FromWasmAbi for TypstCorePngOptions {
+    type Abi = <JsValue as FromWasmAbi>::Abi;
+
+    unsafe fn from_abi(js: Self::Abi) -> Self {
+        serde_wasm_bindgen::from_value(JsValue::from_abi(js)).unwrap()
+    }
+}
@@ -4,64 +4,62 @@ use serde::{Deserialize, Serialize};
 use typst::syntax::{FileId, Source, Span};
 use wasm_bindgen::{prelude::wasm_bindgen};
 
-use crate::{console_log, typst::source_file::SourceFile, typst_error};
+use crate::{typst::source_file::SourceFile, typst_error};
 
 use super::error::TypstCoreError;
 
 
 #[wasm_bindgen(getter_with_clone)]
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct TypstCoreRange {
     pub path: String,
+    /// The package the file belongs to, e.g. `@preview/example:0.1.0`.
+    pub package: Option<String>,
     pub monaco_range: MonacoRange,
 }
 
 impl TypstCoreRange {
+    /// A range covering no text in the file with the given id.
+    pub fn in_file(id: FileId) -> Self {
+        Self {
+            path: id.vpath().as_rooted_path().to_string_lossy().to_string(),
+            package: id.package().map(|package| package.to_string()),
+            monaco_range: MonacoRange::default(),
+        }
+    }
+
     pub fn with_source(span: Span, source: &Source) -> Result<Self, TypstCoreError> {
         if span.is_detached() {
-            Ok(Self {
-                path: String::new(),
-                monaco_range: MonacoRange::default(),
-            })
+            Ok(Self::default())
         } else {
             let range = source
                 .range(span)
                 .ok_or_else(|| typst_error!(format!("Failed to get range for span: {:?}", span)))?;
 
             Ok(Self {
-                path: source
-                    .id()
-                    .vpath()
-                    .as_rooted_path()
-                    .to_string_lossy()
-                    .to_string(),
-                monaco_range: MonacoRange::from_typst_range(range, source)
+                monaco_range: MonacoRange::from_typst_range(range, source),
+                ..Self::in_file(source.id())
             })
         }
     }
 
-    pub fn with_sources(
-        span: Span,
-        sources: &HashMap<FileId, SourceFile>,
-    ) -> Result<Self, TypstCoreError> {
-        if span.is_detached() {
-            Ok(Self {
-                path: String::new(),
-                monaco_range: MonacoRange::default(),
-            })
-        } else {
-            let id = span
-                .id()
-                .ok_or_else(|| typst_error!(format!("Failed to get id for span: {:?}", span)))?;
-
-            let source_file = sources
-                .get(&id)
-                .ok_or_else(|| typst_error!(format!("Failed to get source for id: {:?}", id)))?;
+    /// Resolves a span against the given sources, `None` if it is detached or can't be found.
+    pub fn resolve(span: Span, sources: &HashMap<FileId, SourceFile>) -> Option<Self> {
+        let source = sources.get(&span.id()?)?.source();
+        source.range(span)?;
+        TypstCoreRange::with_source(span, &source).ok()
+    }
 
-            let source = source_file.source();
+    /// Like `resolve`, but falls back to an empty range in the span's file, if known.
+    pub fn with_sources(span: Span, sources: &HashMap<FileId, SourceFile>) -> Self {
+        Self::resolve(span, sources)
+            .or_else(|| span.id().map(Self::in_file))
+            .unwrap_or_default()
+    }
 
-            TypstCoreRange::with_source(span, &source)
-        }
+    /// Whether the range points into a user file rather than a package.
+    pub fn is_user_file(&self) -> bool {
+        self.package.is_none() && !self.path.is_empty()
     }
 }
 
@@ -101,8 +99,10 @@ impl MonacoRange {
             source: &Source,
             offset: usize,
         ) -> Option<(usize, usize)> {
+            // Monaco counts columns in UTF-16 code units, not in chars like `Source::byte_to_column`.
             let line = source.byte_to_line(offset)?;
-            let column = source.byte_to_column(offset)?;
+            let line_start = source.byte_to_utf16(source.line_to_byte(line)?)?;
+            let column = source.byte_to_utf16(offset)? - line_start;
             Some((line, column))
         }
 
@@ -122,17 +122,12 @@ impl MonacoRange {
         }
     }
 
-    pub fn to_typst_range(&self, source: &Source) -> Range<usize> {
-        console_log!("Range: {} {} {} {}", self.begin_line_number, self.begin_column, self.end_line_number, self.end_column);
-
-        let begin = MonacoPosition::new(self.begin_line_number, self.begin_column).to_typst_position(source);
-        let end = MonacoPosition::new(self.end_line_number, self.end_column).to_typst_position(source);
-        if let (Some(begin), Some(end)) = (begin, end) {
-            begin..end
-        } else {
-            0..0
-        }
-    } 
+    /// Gets the byte range in the source, `None` if either end is outside of it or the end lies before the start.
+    pub fn to_typst_range(&self, source: &Source) -> Option<Range<usize>> {
+        let begin = MonacoPosition::new(self.begin_line_number, self.begin_column).to_typst_position(source)?;
+        let end = MonacoPosition::new(self.end_line_number, self.end_column).to_typst_position(source)?;
+        (begin <= end).then_some(begin..end)
+    }
 }
 
 #[wasm_bindgen]
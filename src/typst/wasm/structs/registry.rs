@@ -0,0 +1,75 @@
+use js_sys::Function;
+use wasm_bindgen::{prelude::wasm_bindgen, JsCast, JsValue};
+
+use super::{error::TypstCoreError, package::TypstCorePackage};
+
+/// The registry used for `@preview` packages unless configured otherwise.
+pub const PREVIEW_TEMPLATE: &str = "https://packages.typst.org/preview/{name}-{version}.tar.gz";
+
+/// A JS callback resolving packages of a namespace.
+pub struct JsResolver(Function);
+
+// SAFETY: Without the atomics feature wasm32 is single-threaded, so the function never leaves the thread
+// it was created on. With threads enabled JS values must not be shared, so the core is neither `Send` nor `Sync`
+// and cannot be used as a `World` there.
+#[cfg(not(target_feature = "atomics"))]
+unsafe impl Send for JsResolver {}
+#[cfg(not(target_feature = "atomics"))]
+unsafe impl Sync for JsResolver {}
+
+/// Where the packages of a namespace come from.
+pub enum TypstCoreRegistry {
+    /// A URL template, `{namespace}`, `{name}` and `{version}` are substituted.
+    Template(String),
+    /// A JS function called with the `TypstCorePackage`.
+    Resolver(JsResolver),
+}
+
+impl TypstCoreRegistry {
+    pub fn resolver(function: Function) -> Self {
+        TypstCoreRegistry::Resolver(JsResolver(function))
+    }
+
+    /// Finds where to download the package from, a resolver throwing counts as a failed download.
+    pub fn locate(&self, package: TypstCorePackage) -> Result<TypstCorePackageLocation, TypstCoreError> {
+        match self {
+            TypstCoreRegistry::Template(template) => {
+                let url = template
+                    .replace("{namespace}", &package.namespace)
+                    .replace("{name}", &package.name)
+                    .replace("{version}", &package.version);
+                Ok(TypstCorePackageLocation {
+                    package,
+                    url: Some(url),
+                    resolved: JsValue::UNDEFINED,
+                })
+            }
+            TypstCoreRegistry::Resolver(JsResolver(function)) => {
+                let resolved = function
+                    .call1(&JsValue::NULL, &package.clone().into())
+                    .map_err(|e| TypstCoreError::NetworkFailed {
+                        message: e
+                            .dyn_ref::<js_sys::Error>()
+                            .map(|error| String::from(error.message()))
+                            .or_else(|| e.as_string()),
+                    })?;
+                Ok(TypstCorePackageLocation {
+                    package,
+                    url: None,
+                    resolved,
+                })
+            }
+        }
+    }
+}
+
+/// Where to download a package that has to be installed before compiling.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCorePackageLocation {
+    pub package: TypstCorePackage,
+    /// The tarball URL if the namespace is configured with a template.
+    pub url: Option<String>,
+    /// Whatever the resolver callback returned, e.g. a URL or a promise of the tarball bytes.
+    pub resolved: JsValue,
+}
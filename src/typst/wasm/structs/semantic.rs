@@ -0,0 +1,347 @@
+use typst::syntax::{LinkedNode, Source, SyntaxKind};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// The token types, their index in this list is the type in the encoded tokens.
+///
+/// - `comment`: line and block comments
+/// - `string`: string literals
+/// - `keyword`: `let`, `set`, `show`, `if`, `none`, `and`, ...
+/// - `operator`: operators in code, and attachments, fractions, roots and align points in math
+/// - `number`: integers, floats, numbers with units and numbers in math
+/// - `function`: the callee of a call, the target of a set rule and the selector of a show rule
+/// - `variable`: all other identifiers and letters in math
+/// - `bool`: `true` and `false`
+/// - `punctuation`: brackets, commas, colons and dots in code
+/// - `escape`: escapes like `\#` and shorthands like `--`
+/// - `link`: URLs in markup
+/// - `raw`: raw blocks including their delimiters and language tag
+/// - `label`: labels like `<intro>`
+/// - `ref`: references like `@intro`
+/// - `heading`: the text of a heading
+/// - `strong`: the text of `*strong*` markup, including the stars
+/// - `emph`: the text of `_emph_` markup, including the underscores
+/// - `marker`: heading, list, enum and term markers
+/// - `term`: the term of a term list item
+/// - `delimiter`: the dollars of an equation and the delimiters of `(...)` in math
+/// - `error`: syntax errors
+pub const TOKEN_TYPES: [&str; 21] = [
+    "comment",
+    "string",
+    "keyword",
+    "operator",
+    "number",
+    "function",
+    "variable",
+    "bool",
+    "punctuation",
+    "escape",
+    "link",
+    "raw",
+    "label",
+    "ref",
+    "heading",
+    "strong",
+    "emph",
+    "marker",
+    "term",
+    "delimiter",
+    "error",
+];
+
+/// The token modifiers, the bit `1 << index` is set in the encoded tokens if a modifier applies.
+///
+/// - `math`: the token is inside an equation
+/// - `strong`: the token is inside `*strong*` markup
+/// - `emph`: the token is inside `_emph_` markup
+pub const TOKEN_MODIFIERS: [&str; 3] = ["math", "strong", "emph"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum TokenType {
+    Comment,
+    String,
+    Keyword,
+    Operator,
+    Number,
+    Function,
+    Variable,
+    Bool,
+    Punctuation,
+    Escape,
+    Link,
+    Raw,
+    Label,
+    Ref,
+    Heading,
+    Strong,
+    Emph,
+    Marker,
+    Term,
+    Delimiter,
+    Error,
+}
+
+const MATH: u32 = 1 << 0;
+const STRONG: u32 = 1 << 1;
+const EMPH: u32 = 1 << 2;
+
+/// Whether the identifier is called, used as a set rule target or as a show rule selector.
+fn is_callee(leaf: &LinkedNode) -> bool {
+    let Some(parent) = leaf.parent() else {
+        return false;
+    };
+    match parent.kind() {
+        SyntaxKind::FuncCall => leaf.index() == 0,
+        SyntaxKind::SetRule => leaf.prev_sibling_kind() == Some(SyntaxKind::Set),
+        SyntaxKind::ShowRule => leaf.prev_sibling_kind() == Some(SyntaxKind::Show),
+        // The method in `x.at(0)`.
+        SyntaxKind::FieldAccess => {
+            leaf.index() != 0 && parent.parent_kind() == Some(SyntaxKind::FuncCall) && parent.index() == 0
+        }
+        _ => false,
+    }
+}
+
+fn modifiers(leaf: &LinkedNode) -> u32 {
+    let mut modifiers = 0;
+    let mut node = leaf.parent();
+    while let Some(ancestor) = node {
+        modifiers |= match ancestor.kind() {
+            SyntaxKind::Equation | SyntaxKind::Math => MATH,
+            SyntaxKind::Strong => STRONG,
+            SyntaxKind::Emph => EMPH,
+            _ => 0,
+        };
+        node = ancestor.parent();
+    }
+    modifiers
+}
+
+/// The type of markup text, which depends on the element it is in.
+fn text_type(leaf: &LinkedNode) -> Option<TokenType> {
+    let mut node = leaf.parent();
+    while let Some(ancestor) = node {
+        match ancestor.kind() {
+            SyntaxKind::Heading => return Some(TokenType::Heading),
+            SyntaxKind::Strong => return Some(TokenType::Strong),
+            SyntaxKind::Emph => return Some(TokenType::Emph),
+            SyntaxKind::Markup if ancestor.parent_kind() == Some(SyntaxKind::TermItem) => {
+                // The term comes before the colon, the description after it.
+                return (ancestor.prev_sibling_kind() != Some(SyntaxKind::Colon)).then_some(TokenType::Term);
+            }
+            SyntaxKind::ContentBlock | SyntaxKind::Code => return None,
+            _ => {}
+        }
+        node = ancestor.parent();
+    }
+    None
+}
+
+fn token_type(leaf: &LinkedNode) -> Option<TokenType> {
+    let parent_kind = leaf.parent_kind();
+    Some(match leaf.kind() {
+        SyntaxKind::LineComment | SyntaxKind::BlockComment => TokenType::Comment,
+        SyntaxKind::Str => TokenType::String,
+        SyntaxKind::Let
+        | SyntaxKind::Set
+        | SyntaxKind::Show
+        | SyntaxKind::Context
+        | SyntaxKind::If
+        | SyntaxKind::Else
+        | SyntaxKind::For
+        | SyntaxKind::In
+        | SyntaxKind::While
+        | SyntaxKind::Break
+        | SyntaxKind::Continue
+        | SyntaxKind::Return
+        | SyntaxKind::Import
+        | SyntaxKind::Include
+        | SyntaxKind::As
+        | SyntaxKind::None
+        | SyntaxKind::Auto
+        | SyntaxKind::Not
+        | SyntaxKind::And
+        | SyntaxKind::Or => TokenType::Keyword,
+        SyntaxKind::Bool => TokenType::Bool,
+        SyntaxKind::Int | SyntaxKind::Float | SyntaxKind::Numeric => TokenType::Number,
+        SyntaxKind::Ident | SyntaxKind::MathIdent if is_callee(leaf) => TokenType::Function,
+        SyntaxKind::Ident | SyntaxKind::MathIdent => TokenType::Variable,
+        SyntaxKind::MathText if leaf.text().chars().all(|c| c.is_ascii_digit() || c == '.') => TokenType::Number,
+        SyntaxKind::MathText => TokenType::Variable,
+        SyntaxKind::Escape | SyntaxKind::Shorthand | SyntaxKind::MathShorthand => TokenType::Escape,
+        SyntaxKind::Link => TokenType::Link,
+        SyntaxKind::Label => TokenType::Label,
+        SyntaxKind::Ref | SyntaxKind::RefMarker => TokenType::Ref,
+        SyntaxKind::RawDelim | SyntaxKind::RawLang | SyntaxKind::RawTrimmed => TokenType::Raw,
+        SyntaxKind::Text if parent_kind == Some(SyntaxKind::Raw) => TokenType::Raw,
+        SyntaxKind::HeadingMarker | SyntaxKind::ListMarker | SyntaxKind::EnumMarker | SyntaxKind::TermMarker => {
+            TokenType::Marker
+        }
+        SyntaxKind::Dollar => TokenType::Delimiter,
+        SyntaxKind::Star if parent_kind == Some(SyntaxKind::Strong) => TokenType::Strong,
+        SyntaxKind::Underscore if parent_kind == Some(SyntaxKind::Emph) => TokenType::Emph,
+        _ if parent_kind == Some(SyntaxKind::MathDelimited)
+            && (leaf.prev_sibling().is_none() || leaf.next_sibling().is_none()) =>
+        {
+            TokenType::Delimiter
+        }
+        SyntaxKind::Hat
+        | SyntaxKind::Underscore
+        | SyntaxKind::Slash
+        | SyntaxKind::Prime
+        | SyntaxKind::Root
+        | SyntaxKind::MathAlignPoint
+            if matches!(
+                parent_kind,
+                Some(
+                    SyntaxKind::MathAttach
+                        | SyntaxKind::MathFrac
+                        | SyntaxKind::MathRoot
+                        | SyntaxKind::MathPrimes
+                        | SyntaxKind::Math
+                )
+            ) =>
+        {
+            TokenType::Operator
+        }
+        SyntaxKind::Plus
+        | SyntaxKind::Minus
+        | SyntaxKind::Star
+        | SyntaxKind::Slash
+        | SyntaxKind::Eq
+        | SyntaxKind::EqEq
+        | SyntaxKind::ExclEq
+        | SyntaxKind::Lt
+        | SyntaxKind::LtEq
+        | SyntaxKind::Gt
+        | SyntaxKind::GtEq
+        | SyntaxKind::PlusEq
+        | SyntaxKind::HyphEq
+        | SyntaxKind::StarEq
+        | SyntaxKind::SlashEq
+        | SyntaxKind::Dots
+        | SyntaxKind::Arrow => TokenType::Operator,
+        SyntaxKind::LeftBrace
+        | SyntaxKind::RightBrace
+        | SyntaxKind::LeftBracket
+        | SyntaxKind::RightBracket
+        | SyntaxKind::LeftParen
+        | SyntaxKind::RightParen
+        | SyntaxKind::Comma
+        | SyntaxKind::Semicolon
+        | SyntaxKind::Colon
+        | SyntaxKind::Dot => TokenType::Punctuation,
+        // The hash takes the type of the expression it embeds.
+        SyntaxKind::Hash => return leaf.next_sibling()?.leftmost_leaf().and_then(|leaf| token_type(&leaf)),
+        SyntaxKind::Error => TokenType::Error,
+        SyntaxKind::Text | SyntaxKind::SmartQuote => text_type(leaf)?,
+        _ => return None,
+    })
+}
+
+/// Encodes the tokens of a source the way Monaco expects them: five numbers per token, the line delta, the start
+/// delta (relative to the previous token if on the same line), the length, the type and the modifiers.
+/// Positions and lengths are in UTF-16 code units, tokens spanning several lines are split per line.
+pub fn encode_tokens(source: &Source) -> Vec<u32> {
+    fn walk(source: &Source, node: &LinkedNode, data: &mut Vec<u32>, last: &mut (usize, usize)) {
+        if node.children().len() > 0 {
+            for child in node.children() {
+                walk(source, &child, data, last);
+            }
+            return;
+        }
+
+        let Some(ty) = token_type(node) else {
+            return;
+        };
+        let modifiers = modifiers(node);
+
+        let mut offset = node.offset();
+        for segment in node.text().split_inclusive('\n') {
+            let text = segment.trim_end_matches(['\n', '\r']);
+            let length = text.encode_utf16().count();
+            if length > 0 {
+                let line = source.byte_to_line(offset).unwrap_or_default();
+                let line_start = source.line_to_byte(line).and_then(|byte| source.byte_to_utf16(byte)).unwrap_or_default();
+                let column = source.byte_to_utf16(offset).unwrap_or_default() - line_start;
+
+                let (last_line, last_column) = *last;
+                let delta_column = if line == last_line { column - last_column } else { column };
+                data.extend([
+                    (line - last_line) as u32,
+                    delta_column as u32,
+                    length as u32,
+                    ty as u32,
+                    modifiers,
+                ]);
+                *last = (line, column);
+            }
+            offset += segment.len();
+        }
+    }
+
+    let mut data = Vec::new();
+    walk(source, &LinkedNode::new(source.root()), &mut data, &mut (0, 0));
+    data
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreSemanticTokensLegend {
+    pub token_types: Vec<String>,
+    pub token_modifiers: Vec<String>,
+}
+
+impl Default for TypstCoreSemanticTokensLegend {
+    fn default() -> Self {
+        Self {
+            token_types: TOKEN_TYPES.iter().map(|ty| ty.to_string()).collect(),
+            token_modifiers: TOKEN_MODIFIERS.iter().map(|modifier| modifier.to_string()).collect(),
+        }
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreSemanticTokens {
+    /// Identifies these tokens when asking for a delta later.
+    pub result_id: String,
+    pub data: Vec<u32>,
+}
+
+/// Replaces `delete_count` numbers at `start` of the previous tokens with `data`.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TypstCoreSemanticTokensEdit {
+    pub start: usize,
+    pub delete_count: usize,
+    pub data: Vec<u32>,
+}
+
+impl TypstCoreSemanticTokensEdit {
+    /// A single edit turning `previous` into `current`, `None` if they are equal.
+    pub fn between(previous: &[u32], current: &[u32]) -> Option<Self> {
+        let prefix = previous.iter().zip(current).take_while(|(a, b)| a == b).count();
+        let suffix = previous[prefix..]
+            .iter()
+            .rev()
+            .zip(current[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+
+        (prefix != previous.len() || prefix != current.len()).then(|| Self {
+            start: prefix,
+            delete_count: previous.len() - prefix - suffix,
+            data: current[prefix..current.len() - suffix].to_vec(),
+        })
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreSemanticTokensDelta {
+    pub result_id: String,
+    /// The edits to the tokens of the previous result.
+    pub edits: Vec<TypstCoreSemanticTokensEdit>,
+    /// All tokens, set instead of `edits` if the previous result is unknown.
+    pub data: Option<Vec<u32>>,
+}
@@ -0,0 +1,155 @@
+use typst::{
+    foundations::{Func, Value},
+    syntax::{LinkedNode, Side, Source},
+};
+use typst_ide::Definition;
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::{
+    ast::{enclosing_call, parse_let_binding, ActiveArg},
+    typst::{tidy::{TidyDocs, TidyParam}, TypstCore},
+};
+
+use super::{definition::parse_param, error::TypstCoreError};
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreParameter {
+    /// The parameter as it appears in the signature label.
+    pub label: String,
+    pub docs: Option<String>,
+}
+
+/// A parameter together with what is needed to match it against the argument at the cursor.
+struct Param {
+    info: TypstCoreParameter,
+    name: String,
+    positional: bool,
+    named: bool,
+    variadic: bool,
+}
+
+impl From<TidyParam> for Param {
+    fn from(param: TidyParam) -> Self {
+        let named = param.default.is_some();
+        Self {
+            info: TypstCoreParameter {
+                label: param.label,
+                docs: (!param.description.is_empty()).then_some(param.description),
+            },
+            name: param.name,
+            positional: !named,
+            named,
+            variadic: param.variadic,
+        }
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreSignatureHelp {
+    /// The full signature, e.g. `rect(width?: auto | relative = auto, ...)`.
+    pub label: String,
+    pub docs: Option<String>,
+    pub parameters: Vec<TypstCoreParameter>,
+    /// The index into `parameters` of the argument at the cursor.
+    pub active_parameter: Option<usize>,
+}
+
+impl TypstCoreSignatureHelp {
+    /// Builds the signature help for the call around the cursor, if any.
+    pub fn new(core: &TypstCore, source: &Source, cursor: usize) -> Result<Option<Self>, TypstCoreError> {
+        let root = LinkedNode::new(source.root());
+        let Some((callee, active)) = root
+            .leaf_at(cursor, Side::Before)
+            .and_then(|leaf| enclosing_call(&leaf, cursor))
+        else {
+            return Ok(None);
+        };
+
+        let (doc, main) = core.focus(source.id());
+        let world = core.focused(main);
+        let definition = typst_ide::definition(&world, doc.as_ref(), source, callee.offset(), Side::After);
+        let signature = match definition {
+            Some(Definition::Span(span)) => core
+                .resolve_span(span, |node, _, _| {
+                    parse_let_binding(&node).map(|(binding, args)| {
+                        let docs = TidyDocs::from_binding(&binding, &args);
+                        let params = docs.params.into_iter().map(Param::from).collect::<Vec<_>>();
+                        (binding.0, (!docs.description.is_empty()).then_some(docs.description), params)
+                    })
+                })?
+                .flatten(),
+            Some(Definition::Std(Value::Func(func))) => Self::native_signature(&func),
+            _ => typst_ide::analyze_expr(&world, &callee)
+                .into_iter()
+                .find_map(|(value, _)| match value {
+                    Value::Func(func) => Self::native_signature(&func),
+                    _ => None,
+                }),
+        };
+
+        Ok(signature.map(|(name, docs, params)| {
+            let active_parameter = Self::active_parameter(&params, &active);
+            let parameters = params.into_iter().map(|param| param.info).collect::<Vec<_>>();
+            let label = format!(
+                "{}({})",
+                name,
+                parameters
+                    .iter()
+                    .map(|param| param.label.as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            );
+
+            Self {
+                label,
+                docs,
+                parameters,
+                active_parameter,
+            }
+        }))
+    }
+
+    fn native_signature(func: &Func) -> Option<(String, Option<String>, Vec<Param>)> {
+        let params = func
+            .params()?
+            .iter()
+            .map(|param| Param {
+                info: TypstCoreParameter {
+                    label: parse_param(param),
+                    docs: (!param.docs.is_empty()).then(|| param.docs.to_string()),
+                },
+                name: param.name.to_string(),
+                positional: param.positional,
+                named: param.named,
+                variadic: param.variadic,
+            })
+            .collect();
+
+        Some((
+            func.name().unwrap_or("function").to_string(),
+            func.docs().map(str::to_string),
+            params,
+        ))
+    }
+
+    /// Matches the argument at the cursor against the parameters.
+    ///
+    /// Positional arguments fill the positional parameters in order, up to a variadic one, which takes all
+    /// arguments after that.
+    fn active_parameter(params: &[Param], active: &ActiveArg) -> Option<usize> {
+        match active {
+            ActiveArg::Named(name) => params.iter().position(|param| param.named && &param.name == name),
+            ActiveArg::Positional(index) => {
+                let positional = params
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, param)| param.positional)
+                    .collect::<Vec<_>>();
+                let fixed = positional.iter().take_while(|(_, param)| !param.variadic).count();
+                positional.get((*index).min(fixed)).map(|(i, _)| *i)
+            }
+        }
+    }
+}
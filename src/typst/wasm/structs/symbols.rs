@@ -0,0 +1,274 @@
+use std::collections::HashMap;
+
+use typst::{
+    comemo::Track,
+    engine::{Engine, Route, Sink, Traced},
+    foundations::{NativeElement, StyleChain},
+    introspection::Counter,
+    layout::PagedDocument,
+    model::{HeadingElem, Numbering},
+    syntax::{ast::{self, AstNode}, FileId, LinkedNode, Source},
+    World,
+};
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::typst::source_file::SourceFile;
+
+use super::range::{MonacoRange, TypstCoreRange};
+
+#[wasm_bindgen]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TypstCoreSymbolKind {
+    Heading,
+    Function,
+    Variable,
+    Label,
+    Figure,
+    ShowRule,
+    SetRule,
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreDocumentSymbol {
+    pub name: String,
+    /// E.g. the parameters of a function or the caption of a figure.
+    pub detail: Option<String>,
+    pub kind: TypstCoreSymbolKind,
+    /// The level of a heading, starting at 1.
+    pub level: Option<usize>,
+    /// The range of the whole symbol.
+    pub range: MonacoRange,
+    /// The range of the symbol's name.
+    pub selection_range: MonacoRange,
+    /// The symbols nested in this one. Everything up to the next heading of the same or a lower level
+    /// is nested in a heading.
+    pub children: Vec<TypstCoreDocumentSymbol>,
+}
+
+impl TypstCoreDocumentSymbol {
+    fn new(
+        source: &Source,
+        node: &LinkedNode,
+        name_node: Option<&LinkedNode>,
+        name: String,
+        kind: TypstCoreSymbolKind,
+    ) -> Self {
+        let range = MonacoRange::from_typst_range(node.range(), source);
+        Self {
+            name,
+            detail: None,
+            kind,
+            level: None,
+            selection_range: name_node.map_or_else(
+                || range.clone(),
+                |name_node| MonacoRange::from_typst_range(name_node.range(), source),
+            ),
+            range,
+            children: Vec::new(),
+        }
+    }
+
+    /// Collects the symbols of a source, nested by headings.
+    pub fn from_source(source: &Source) -> Vec<Self> {
+        Self::collect(source, &LinkedNode::new(source.root()))
+    }
+
+    fn collect(source: &Source, node: &LinkedNode) -> Vec<Self> {
+        let mut symbols = Vec::new();
+        for child in node.children() {
+            match Self::from_node(source, &child) {
+                Some(mut found) => {
+                    for symbol in &mut found {
+                        symbol.children = Self::collect(source, &child);
+                    }
+                    symbols.extend(found);
+                }
+                None => symbols.extend(Self::collect(source, &child)),
+            }
+        }
+        Self::nest_headings(symbols)
+    }
+
+    /// The symbols a node defines itself, without the ones of its children.
+    fn from_node(source: &Source, node: &LinkedNode) -> Option<Vec<Self>> {
+        if let Some(heading) = node.cast::<ast::Heading>() {
+            let body = node.find(heading.body().span());
+            let name = heading.body().to_untyped().clone().into_text().trim().to_string();
+            let mut symbol = Self::new(source, node, body.as_ref(), name, TypstCoreSymbolKind::Heading);
+            symbol.level = Some(heading.depth().get());
+            return Some(vec![symbol]);
+        }
+
+        if let Some(binding) = node.cast::<ast::LetBinding>() {
+            return Some(match binding.kind() {
+                ast::LetBindingKind::Closure(ident) => {
+                    let name_node = node.find(ident.span());
+                    let mut symbol = Self::new(
+                        source,
+                        node,
+                        name_node.as_ref(),
+                        ident.get().to_string(),
+                        TypstCoreSymbolKind::Function,
+                    );
+                    if let Some(ast::Expr::Closure(closure)) = binding.init() {
+                        symbol.detail = Some(closure.params().to_untyped().clone().into_text().to_string());
+                    }
+                    vec![symbol]
+                }
+                ast::LetBindingKind::Normal(pattern) => pattern
+                    .bindings()
+                    .into_iter()
+                    .map(|ident| {
+                        let name_node = node.find(ident.span());
+                        Self::new(
+                            source,
+                            node,
+                            name_node.as_ref(),
+                            ident.get().to_string(),
+                            TypstCoreSymbolKind::Variable,
+                        )
+                    })
+                    .collect(),
+            });
+        }
+
+        if let Some(label) = node.cast::<ast::Label>() {
+            return Some(vec![Self::new(
+                source,
+                node,
+                None,
+                label.get().to_string(),
+                TypstCoreSymbolKind::Label,
+            )]);
+        }
+
+        if let Some(set) = node.cast::<ast::SetRule>() {
+            let target = node.find(set.target().span());
+            let name = format!("set {}", set.target().to_untyped().clone().into_text());
+            return Some(vec![Self::new(source, node, target.as_ref(), name, TypstCoreSymbolKind::SetRule)]);
+        }
+
+        if let Some(show) = node.cast::<ast::ShowRule>() {
+            let selector = show.selector().and_then(|selector| node.find(selector.span()));
+            let name = match show.selector() {
+                Some(selector) => format!("show {}", selector.to_untyped().clone().into_text()),
+                None => "show".to_string(),
+            };
+            return Some(vec![Self::new(source, node, selector.as_ref(), name, TypstCoreSymbolKind::ShowRule)]);
+        }
+
+        if let Some(call) = node.cast::<ast::FuncCall>() {
+            if matches!(call.callee(), ast::Expr::Ident(ident) if ident.as_str() == "figure") {
+                let callee = node.find(call.callee().span());
+                let mut symbol = Self::new(source, node, callee.as_ref(), "figure".to_string(), TypstCoreSymbolKind::Figure);
+                symbol.detail = call.args().items().find_map(|arg| match arg {
+                    ast::Arg::Named(named) if named.name().as_str() == "caption" => {
+                        let caption = named.expr().to_untyped().clone().into_text();
+                        Some(caption.trim_start_matches('[').trim_end_matches(']').trim().to_string())
+                    }
+                    _ => None,
+                });
+                return Some(vec![symbol]);
+            }
+        }
+
+        None
+    }
+
+    /// Moves every symbol following a heading into it, until the next heading of the same or a lower level.
+    fn nest_headings(symbols: Vec<Self>) -> Vec<Self> {
+        fn close(open: &mut Vec<TypstCoreDocumentSymbol>, nested: &mut Vec<TypstCoreDocumentSymbol>) {
+            if let Some(heading) = open.pop() {
+                match open.last_mut() {
+                    Some(parent) => parent.children.push(heading),
+                    None => nested.push(heading),
+                }
+            }
+        }
+
+        let mut nested = Vec::new();
+        let mut open: Vec<Self> = Vec::new();
+        for symbol in symbols {
+            if let Some(level) = symbol.level {
+                while open.last().is_some_and(|heading| heading.level >= Some(level)) {
+                    close(&mut open, &mut nested);
+                }
+                open.push(symbol);
+            } else {
+                match open.last_mut() {
+                    Some(heading) => heading.children.push(symbol),
+                    None => nested.push(symbol),
+                }
+            }
+        }
+        while !open.is_empty() {
+            close(&mut open, &mut nested);
+        }
+        nested
+    }
+}
+
+/// A heading of the compiled document.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreOutlineItem {
+    pub title: String,
+    pub level: usize,
+    /// The displayed number, e.g. `1.2.`. Not set for unnumbered headings and numbering functions.
+    pub numbering: Option<String>,
+    /// The one-indexed page the heading is on.
+    pub page: usize,
+    pub range: Option<TypstCoreRange>,
+}
+
+impl TypstCoreOutlineItem {
+    /// Lists the headings of a document, numbered by the value of the heading counter at each of them,
+    /// so `counter(heading).update(..)` is taken into account.
+    pub fn from_document(world: &dyn World, doc: &PagedDocument, sources: &HashMap<FileId, SourceFile>) -> Vec<Self> {
+        let traced = Traced::default();
+        let mut sink = Sink::new();
+        let mut engine = Engine {
+            routines: &typst::ROUTINES,
+            world: world.track(),
+            introspector: doc.introspector.track(),
+            traced: traced.track(),
+            sink: sink.track_mut(),
+            route: Route::default(),
+        };
+        let counter = Counter::of(HeadingElem::elem());
+
+        doc.introspector
+            .query(&HeadingElem::elem().select())
+            .iter()
+            .filter_map(|content| {
+                let heading = content.to_packed::<HeadingElem>()?;
+                let numbering = match (heading.numbering(StyleChain::default()), content.location()) {
+                    (Some(Numbering::Pattern(pattern)), Some(location)) => counter
+                        .at_loc(&mut engine, location)
+                        .ok()
+                        .map(|state| pattern.apply(&state.0).to_string()),
+                    _ => None,
+                };
+
+                Some(Self {
+                    title: heading.body.plain_text().to_string(),
+                    level: heading.resolve_level(StyleChain::default()).get(),
+                    numbering,
+                    page: content
+                        .location()
+                        .map_or(1, |location| doc.introspector.page(location).get()),
+                    range: TypstCoreRange::resolve(content.span(), sources),
+                })
+            })
+            .collect()
+    }
+}
+
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Clone, Debug)]
+pub struct TypstCoreDocumentSymbols {
+    pub symbols: Vec<TypstCoreDocumentSymbol>,
+    /// The headings of the last compiled document, empty if nothing was compiled yet.
+    pub outline: Vec<TypstCoreOutlineItem>,
+}
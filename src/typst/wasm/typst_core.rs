@@ -1,19 +1,21 @@
 use std::{
     collections::{HashMap, HashSet},
+    ops::Range,
     sync::{Arc, Mutex, OnceLock},
 };
 
 use atomic_refcell::AtomicRefCell;
 use typst::{
-    foundations::Bytes, html::HtmlDocument, layout::PagedDocument, syntax::{FileId, VirtualPath}, text::{Font, FontBook}, utils::LazyHash
+    diag::SourceDiagnostic, foundations::Bytes, html::HtmlDocument, layout::{Abs, PagedDocument, Point}, syntax::{package::PackageSpec, FileId, LinkedNode, Side, Source, VirtualPath}, text::{Font, FontBook}, utils::{hash128, LazyHash}, World
 };
-use wasm_bindgen::{prelude::wasm_bindgen, JsValue};
+use js_sys::Function;
+use wasm_bindgen::prelude::wasm_bindgen;
 
 use crate::{
-    console_log, typst::{source_file::SourceFile, wasm::structs::{definition::TypstCoreDefinition, diagnostics::TypstCoreDiagnostics, range::{MonacoPosition, MonacoRange}}, TypstCore}, typst_error
+    console_log, typst::{format::format_source, source_file::SourceFile, target::{CompileTarget, DEFAULT_TARGET}, wasm::structs::{definition::TypstCoreDefinition, diagnostics::TypstCoreDiagnostics, range::{MonacoPosition, MonacoRange, TypstCoreRange}}, TypstCore}, typst_error
 };
 
-use super::structs::{error::TypstCoreError, output::{Output, OutputFormat}};
+use super::structs::{code_action::TypstCoreCodeAction, completion::TypstCoreCompletion, docs::TypstCoreModuleDocs, edit::{TypstCoreFileEdit, TypstCoreTextEdit}, error::TypstCoreError, folding::{TypstCoreFoldingRange, TypstCoreSelectionRange}, format::TypstCoreFormatOptions, jump::{TypstCoreJump, TypstCorePagePosition}, font::{styled_range, text_fonts, TypstCoreFontFamily, TypstCoreFontInfo, TypstCoreFontReport, TypstCoreMissingFont}, hover::TypstCoreHover, package::TypstCorePackage, registry::{TypstCorePackageLocation, TypstCoreRegistry, PREVIEW_TEMPLATE}, output::{CompileOutput, Output, OutputFormat, TypstCoreSvgDiff, TypstCoreSvgPage}, pdf::TypstCorePdfOptions, png::TypstCorePngOptions, semantic::{encode_tokens, TypstCoreSemanticTokens, TypstCoreSemanticTokensDelta, TypstCoreSemanticTokensEdit, TypstCoreSemanticTokensLegend}, signature::TypstCoreSignatureHelp, symbols::{TypstCoreDocumentSymbol, TypstCoreDocumentSymbols, TypstCoreOutlineItem}};
 
 fn gather_internal_fonts() -> Vec<Font> {
     let mut fonts = Vec::new();
@@ -28,12 +30,259 @@ fn gather_internal_fonts() -> Vec<Font> {
     fonts
 }
 
+impl TypstCore {
+    /// Replaces the font book with one matching the current fonts.
+    fn rebuild_font_book(&mut self) {
+        let book = FontBook::from_fonts(self.fonts.get_mut().unwrap().iter());
+        self.book = OnceLock::from(LazyHash::new(book));
+    }
+
+    fn to_diagnostics(&self, diagnostics: impl IntoIterator<Item = SourceDiagnostic>) -> Vec<TypstCoreDiagnostics> {
+        let sources = self.sources.borrow();
+        let failures = self.file_failures.lock().unwrap();
+        let no_failures = HashMap::new();
+        diagnostics
+            .into_iter()
+            .map(|diag| TypstCoreDiagnostics::from_diagnostics(diag, &sources, failures.as_ref().unwrap_or(&no_failures)))
+            .collect()
+    }
+
+    fn to_compile_error(&self, errors: impl IntoIterator<Item = SourceDiagnostic>) -> TypstCoreError {
+        TypstCoreError::CompileError(self.to_diagnostics(errors))
+    }
+
+    /// Gets the source of a user file.
+    fn user_source(&self, path: &str) -> Result<Source, TypstCoreError> {
+        let id = FileId::new(None, VirtualPath::new(path));
+        self.sources
+            .borrow()
+            .get(&id)
+            .map(SourceFile::source)
+            .ok_or_else(|| TypstCoreError::file_not_found(path))
+    }
+
+    /// Gets the byte offset of a position in the source of the given path.
+    fn offset_at(path: &str, source: &Source, position: &MonacoPosition) -> Result<usize, TypstCoreError> {
+        position
+            .to_typst_position(source)
+            .ok_or_else(|| TypstCoreError::InvalidPosition {
+                path: path.to_string(),
+                line_number: position.line_number,
+                column: position.column,
+            })
+    }
+
+    /// Gets the source of a user file and the byte offset of the given position in it.
+    fn source_at(&self, path: &str, position: &MonacoPosition) -> Result<(Source, usize), TypstCoreError> {
+        let source = self.user_source(path)?;
+        let cursor = Self::offset_at(path, &source, position)?;
+        Ok((source, cursor))
+    }
+
+    /// Gets the source of a user file and the byte range of the given range in it.
+    ///
+    /// Fails with `InvalidPosition` if either end is outside of the source or the end lies before the start.
+    fn range_at(&self, path: &str, range: &MonacoRange) -> Result<(Source, Range<usize>), TypstCoreError> {
+        let source = self.user_source(path)?;
+        let start = Self::offset_at(path, &source, &MonacoPosition::new(range.begin_line_number, range.begin_column))?;
+        let end_position = MonacoPosition::new(range.end_line_number, range.end_column);
+        let end = Self::offset_at(path, &source, &end_position)?;
+        if end < start {
+            return Err(TypstCoreError::InvalidPosition {
+                path: path.to_string(),
+                line_number: end_position.line_number,
+                column: end_position.column,
+            });
+        }
+        Ok((source, start..end))
+    }
+
+    /// Finds the byte ranges of every reference to the binding at the given position, sorted by file and offset.
+    fn reference_ranges(&self, path: &str, position: &MonacoPosition) -> Result<Vec<(Source, Range<usize>)>, TypstCoreError> {
+        let (source, cursor) = self.source_at(path, position)?;
+        let Some(target) = self.binding_at(&source, cursor) else {
+            return Ok(Vec::new());
+        };
+
+        let sources = self.sources.borrow();
+        let mut ranges = self
+            .find_references(target)
+            .into_iter()
+            .filter_map(|span| {
+                let source = sources.get(&span.id()?)?.source();
+                let range = source.range(span)?;
+                Some((source, range))
+            })
+            .collect::<Vec<_>>();
+        ranges.sort_by_key(|(source, range)| {
+            (source.id().package().map(|package| package.to_string()), source.id().vpath().clone(), range.start)
+        });
+        Ok(ranges)
+    }
+
+    fn format_edits(&self, path: &str, range: Option<MonacoRange>) -> Result<Vec<TypstCoreTextEdit>, TypstCoreError> {
+        let (source, range) = match range {
+            Some(range) => {
+                let (source, range) = self.range_at(path, &range)?;
+                (source, Some(range))
+            }
+            None => (self.user_source(path)?, None),
+        };
+
+        Ok(format_source(&source, range, &self.format_options)
+            .into_iter()
+            .map(|(range, new_text)| TypstCoreTextEdit {
+                range: MonacoRange::from_typst_range(range, &source),
+                new_text,
+            })
+            .collect())
+    }
+
+    /// Compiles the named target from its root file, which becomes `World::main`.
+    fn compile_main(&self, target: &str, root: FileId, format: OutputFormat) -> Result<CompileOutput, TypstCoreError> {
+        *self.main.lock().unwrap() = Some(root);
+        match format {
+            OutputFormat::Html => {
+                let warned = typst::compile::<HtmlDocument>(self);
+                let warnings = self.to_diagnostics(warned.warnings);
+                match warned.output {
+                    Ok(doc) => {
+                        // is not a paged document, so we don't need to store it
+                        let html = typst_html::html(&doc).map_err(|e| self.to_compile_error(e))?;
+
+                        Ok(CompileOutput::new(Output::Html(html), warnings))
+                    }
+                    Err(error) => Err(self.to_compile_error(error)),
+                }
+            }
+            _ => {
+                let warned = typst::compile::<PagedDocument>(self);
+                let missing_fonts = warned
+                    .output
+                    .as_ref()
+                    .map(|doc| self.missing_fonts(&warned.warnings, doc))
+                    .unwrap_or_default();
+                let warnings = self.to_diagnostics(warned.warnings);
+                match warned.output {
+                    Ok(doc) => {
+                        let svg_diff = {
+                            let mut targets = self.targets.lock().unwrap();
+                            let compiled_at = targets.values().map(|target| target.compiled_at).max().unwrap_or(0) + 1;
+                            let target = targets
+                                .get_mut(target)
+                                .ok_or_else(|| TypstCoreError::TargetNotFound { name: target.to_owned() })?;
+                            target.last_doc = Some(doc.clone());
+                            target.missing_fonts = missing_fonts;
+                            target.compiled_at = compiled_at;
+                            matches!(format, OutputFormat::SvgIncremental)
+                                .then(|| Self::svg_diff(&doc, &mut target.svg_page_hashes))
+                        };
+
+                        let output = match (format, svg_diff) {
+                            (_, Some(diff)) => Output::SvgIncremental(diff),
+                            (OutputFormat::Pdf, _) => {
+                                let options = self.pdf_options.to_typst(self)?;
+                                let pdf = typst_pdf::pdf(&doc, &options).map_err(|e| self.to_compile_error(e))?;
+
+                                Output::Pdf(pdf)
+                            }
+                            (OutputFormat::Png, _) => Output::Png(self.png_options.render(&doc)?),
+                            _ => Output::Svg(doc.pages.iter().map(typst_svg::svg).collect()),
+                        };
+                        Ok(CompileOutput::new(output, warnings))
+                    }
+                    Err(error) => Err(self.to_compile_error(error)),
+                }
+            }
+        }
+    }
+
+    /// The error for a target that does not exist, the default one only exists once a root is set.
+    fn unknown_target(name: String) -> TypstCoreError {
+        if name == DEFAULT_TARGET {
+            TypstCoreError::RootNotSet
+        } else {
+            TypstCoreError::TargetNotFound { name }
+        }
+    }
+
+    /// Returns the last document of the named target.
+    fn target_doc(&self, target: &str) -> Result<PagedDocument, TypstCoreError> {
+        let targets = self.targets.lock().unwrap();
+        let target = targets.get(target).ok_or_else(|| Self::unknown_target(target.to_owned()))?;
+        target
+            .last_doc
+            .clone()
+            .ok_or_else(|| typst_error!("No document has been compiled yet"))
+    }
+
+    /// Finds the font families the compiler warned about being unknown and the families the document's text
+    /// styled with them was laid out in.
+    fn missing_fonts(&self, warnings: &[SourceDiagnostic], doc: &PagedDocument) -> Vec<TypstCoreMissingFont> {
+        let mut missing: Vec<TypstCoreMissingFont> = Vec::new();
+        let mut fonts = None;
+        for warning in warnings {
+            let Some(font) = TypstCoreMissingFont::from_warning(&warning.message) else {
+                continue;
+            };
+            let index = match missing.iter().position(|known| known.family == font.family) {
+                Some(index) => index,
+                None => {
+                    missing.push(font);
+                    missing.len() - 1
+                }
+            };
+
+            let styled = self
+                .resolve_span(warning.span, |node, id, _| styled_range(&node).map(|range| (id, range)))
+                .ok()
+                .flatten()
+                .flatten();
+            if let Some((id, range)) = styled {
+                let fonts = fonts.get_or_insert_with(|| text_fonts(doc, &self.sources.borrow()));
+                missing[index].add_used(fonts, id, range);
+            }
+        }
+        missing.sort_by(|a, b| a.family.cmp(&b.family));
+        missing
+    }
+
+    /// Renders the pages whose frame changed since the last call and remembers the new page hashes.
+    fn svg_diff(doc: &PagedDocument, hashes: &mut Vec<u128>) -> TypstCoreSvgDiff {
+        let new_hashes = doc
+            .pages
+            .iter()
+            .map(|page| hash128(&(&page.frame, &page.fill)))
+            .collect::<Vec<_>>();
+
+        let changed = doc
+            .pages
+            .iter()
+            .zip(&new_hashes)
+            .enumerate()
+            .filter(|(index, (_, hash))| hashes.get(*index) != Some(hash))
+            .map(|(index, (page, _))| TypstCoreSvgPage {
+                index,
+                svg: typst_svg::svg(page),
+            })
+            .collect();
+
+        *hashes = new_hashes;
+
+        TypstCoreSvgDiff {
+            page_count: doc.pages.len(),
+            changed,
+        }
+    }
+}
+
 #[wasm_bindgen]
 impl TypstCore {
     #[wasm_bindgen(constructor)]
     pub fn construct() -> Self {
         console_error_panic_hook::set_once();
         let fonts = gather_internal_fonts();
+        let builtin_fonts = fonts.len();
 
         Self {
             library: OnceLock::default(),
@@ -42,86 +291,226 @@ impl TypstCore {
 
             sources: Arc::new(AtomicRefCell::new(HashMap::new())),
 
+            files: Arc::new(AtomicRefCell::new(HashMap::new())),
+
             fonts: Mutex::new(fonts),
 
-            root: None,
+            builtin_fonts,
+
+            targets: Mutex::new(HashMap::new()),
+
+            main: Mutex::new(None),
+
+            accessed: Mutex::new(None),
 
-            last_doc: Mutex::new(None),
+            file_failures: Mutex::new(None),
 
             now: OnceLock::new(),
 
             packages: Mutex::new(HashSet::new()),
+
+            registries: HashMap::from([(
+                "preview".to_owned(),
+                TypstCoreRegistry::Template(PREVIEW_TEMPLATE.to_owned()),
+            )]),
+
+            pdf_options: TypstCorePdfOptions::default(),
+
+            png_options: TypstCorePngOptions::default(),
+
+            format_options: TypstCoreFormatOptions::default(),
+
+            semantic_tokens: Mutex::new(HashMap::new()),
         }
     }
 
-    pub fn compile(&self, format: OutputFormat) -> Result<Output, TypstCoreError> {
-        if self.root.is_none() {
-            return Err(typst_error!("Root path is not set"));
-        }
+    /// Compiles the default target, see `set_root`.
+    pub fn compile(&self, format: OutputFormat) -> Result<CompileOutput, TypstCoreError> {
+        self.compile_target(DEFAULT_TARGET.to_owned(), format)
+    }
 
-        match format {
-            OutputFormat::Html => {
-                match typst::compile::<HtmlDocument>(self).output {
-                    Ok(doc) => {
-                        // is not a paged document, so we don't need to store it
-                        let html = typst_html::html(&doc).map_err(|e| {
-                            let mut diagnostics = Vec::new();
-                            for err in e {
-                                let diag = match TypstCoreDiagnostics::from_diagnostics(err, &self.sources.borrow()) {
-                                    Ok(diag) => diag,
-                                    Err(e) => {
-                                        return e;
-                                    }
-                                };
-                                diagnostics.push(diag);
-                            }
-                            TypstCoreError::CompileError(diagnostics)
-                        })?;
+    /// Compiles the named target and remembers the document and the sources it read.
+    pub fn compile_target(&self, target: String, format: OutputFormat) -> Result<CompileOutput, TypstCoreError> {
+        let root = self.targets.lock().unwrap().get(&target).map(|target| target.root);
+        let Some(root) = root else {
+            return Err(Self::unknown_target(target));
+        };
 
-                        Ok(Output::Html(html))
-                    }
-                    Err(error) => {
-                        let mut diagnostics = Vec::new();
-                        for err in error {
-                            let diag = TypstCoreDiagnostics::from_diagnostics(err, &self.sources.borrow())?;
-                            diagnostics.push(diag);
-                        }
-                        Err(TypstCoreError::CompileError(diagnostics))
-                    }
-                }
-            }
-            _ => {
-                match typst::compile::<PagedDocument>(self).output {
-                    Ok(doc) => {
-                        *self.last_doc.lock().unwrap() = Some(doc.clone());
-        
-                        Ok(Output::Svg(doc.pages.iter().map(typst_svg::svg).collect()))
-                    }
-                    Err(error) => {
-                        let mut diagnostics = Vec::new();
-                        for err in error {
-                            let diag = TypstCoreDiagnostics::from_diagnostics(err, &self.sources.borrow())?;
-                            diagnostics.push(diag);
-                        }
-                        Err(TypstCoreError::CompileError(diagnostics))
-                    }
-                }
-            }
+        *self.accessed.lock().unwrap() = Some(HashSet::new());
+        *self.file_failures.lock().unwrap() = Some(HashMap::new());
+        let result = self.compile_main(&target, root, format);
+        self.file_failures.lock().unwrap().take();
+        let accessed = self.accessed.lock().unwrap().take().unwrap_or_default();
+        if let Some(target) = self.targets.lock().unwrap().get_mut(&target) {
+            target.dependencies = accessed;
         }
+        result
     }
 
+    /// Sets the root file of the default target, compiled by `compile`.
     pub fn set_root(&mut self, path: String) -> Result<(), TypstCoreError> {
-        let sources = self.sources.borrow();
+        self.set_target(DEFAULT_TARGET.to_owned(), path)
+    }
+
+    /// Adds a named target compiled from the given root file, or changes the root of an existing one.
+    pub fn set_target(&mut self, name: String, path: String) -> Result<(), TypstCoreError> {
         let id = FileId::new(None, VirtualPath::new(&path));
-        if sources.contains_key(&id) {
-            self.root = Some(id);
-            Ok(())
-        } else {
-            Err(typst_error!(format!(
-                "Failed to set root, source not found for path: {:?}",
-                path
-            )))
+        if !self.sources.borrow().contains_key(&id) {
+            return Err(TypstCoreError::file_not_found(path));
+        }
+
+        let targets = self.targets.get_mut().unwrap();
+        if targets.get(&name).is_none_or(|target| target.root != id) {
+            targets.insert(name, CompileTarget::new(id));
+        }
+        Ok(())
+    }
+
+    /// Removes a named target together with its last document, removing the default target unsets the root.
+    pub fn remove_target(&mut self, name: String) {
+        self.targets.get_mut().unwrap().remove(&name);
+    }
+
+    /// Lists the names of all targets, sorted.
+    pub fn target_names(&self) -> Vec<String> {
+        let mut names = self.targets.lock().unwrap().keys().cloned().collect::<Vec<_>>();
+        names.sort();
+        names
+    }
+
+    /// Forgets the pages sent by `OutputFormat::SvgIncremental`, so the next compile of each target returns every page.
+    pub fn reset_svg_diff(&self) {
+        for target in self.targets.lock().unwrap().values_mut() {
+            target.svg_page_hashes.clear();
+        }
+    }
+
+    /// Sets the options used when compiling with `OutputFormat::Pdf`.
+    pub fn set_pdf_options(&mut self, options: TypstCorePdfOptions) {
+        self.pdf_options = options;
+    }
+
+    /// Sets the options used when compiling with `OutputFormat::Png`.
+    pub fn set_png_options(&mut self, options: TypstCorePngOptions) {
+        self.png_options = options;
+    }
+
+    /// Sets the options used by `format` and `format_range`.
+    pub fn set_format_options(&mut self, options: TypstCoreFormatOptions) {
+        self.format_options = options;
+    }
+
+    /// Renders a single page (one-indexed) of the default target to PNG bytes.
+    pub fn render_png(&self, page: usize, ppi: f32) -> Result<Vec<u8>, TypstCoreError> {
+        self.render_png_target(DEFAULT_TARGET.to_owned(), page, ppi)
+    }
+
+    /// Renders a single page (one-indexed) of the last document of the named target to PNG bytes.
+    pub fn render_png_target(&self, target: String, page: usize, ppi: f32) -> Result<Vec<u8>, TypstCoreError> {
+        let doc = self.target_doc(&target)?;
+        let page = page
+            .checked_sub(1)
+            .and_then(|index| doc.pages.get(index))
+            .ok_or_else(|| typst_error!(format!("Page {} does not exist", page)))?;
+
+        self.png_options.render_page(page, ppi)
+    }
+
+    /// Lists all fonts of the current font book grouped by family.
+    pub fn list_fonts(&self) -> Vec<TypstCoreFontFamily> {
+        TypstCoreFontFamily::from_book(World::book(self), self.builtin_fonts)
+    }
+
+    /// Reports unavailable font families and the fonts used instead during the last compile of the default target.
+    pub fn font_report(&self) -> Result<TypstCoreFontReport, TypstCoreError> {
+        self.font_report_target(DEFAULT_TARGET.to_owned())
+    }
+
+    /// Reports unavailable font families and the fonts used instead during the last compile of the named target.
+    pub fn font_report_target(&self, target: String) -> Result<TypstCoreFontReport, TypstCoreError> {
+        let targets = self.targets.lock().unwrap();
+        let target = targets.get(&target).ok_or_else(|| Self::unknown_target(target.clone()))?;
+        Ok(TypstCoreFontReport {
+            missing: target.missing_fonts.clone(),
+        })
+    }
+
+    /// Adds all fonts contained in the given font file or collection and makes them available immediately.
+    pub fn add_font(&mut self, data: Vec<u8>) -> Result<Vec<TypstCoreFontInfo>, TypstCoreError> {
+        let added = Font::iter(Bytes::new(data)).collect::<Vec<_>>();
+        if added.is_empty() {
+            return Err(typst_error!("No font could be read from the given data"));
         }
+
+        let infos = added.iter().map(|font| TypstCoreFontInfo::new(font.info(), false)).collect();
+        self.fonts.get_mut().unwrap().extend(added);
+        self.rebuild_font_book();
+        Ok(infos)
+    }
+
+    /// Removes all user supplied fonts of the given family, bundled fonts are kept.
+    pub fn remove_font(&mut self, family: String) -> Vec<TypstCoreFontInfo> {
+        let builtin_fonts = self.builtin_fonts;
+        let fonts = self.fonts.get_mut().unwrap();
+        let mut removed = Vec::new();
+        let mut index = 0;
+        fonts.retain(|font| {
+            let keep = index < builtin_fonts || !font.info().family.eq_ignore_ascii_case(&family);
+            if !keep {
+                removed.push(TypstCoreFontInfo::new(font.info(), false));
+            }
+            index += 1;
+            keep
+        });
+
+        if !removed.is_empty() {
+            self.rebuild_font_book();
+        }
+        removed
+    }
+
+    /// Returns every package imported by a source that is not installed yet, whatever its namespace.
+    ///
+    /// This only scans the sources, the asynchronous part is up to the host: it fetches each package, e.g. from
+    /// where `locate_package` points, and hands the tarball to `install_package`, so compiling never blocks on
+    /// network I/O. Installed packages may import further packages, so call this again until it returns nothing.
+    pub fn resolve_imports(&self) -> Vec<TypstCorePackage> {
+        self.missing_packages().into_iter().map(TypstCorePackage::from).collect()
+    }
+
+    /// Finds where to download a package from through the registry of its namespace.
+    ///
+    /// Fails with `PackageNotFound` if no registry serves the namespace and with `NetworkFailed` if a resolver
+    /// callback throws.
+    pub fn locate_package(&self, package: TypstCorePackage) -> Result<TypstCorePackageLocation, TypstCoreError> {
+        let spec = PackageSpec::try_from(&package)?;
+        let registry = self
+            .registries
+            .get(spec.namespace.as_str())
+            .ok_or_else(|| TypstCoreError::PackageNotFound { spec: spec.to_string() })?;
+        registry.locate(package)
+    }
+
+    /// Serves a namespace from a URL template, `{namespace}`, `{name}` and `{version}` are substituted.
+    pub fn set_registry_template(&mut self, namespace: String, template: String) {
+        self.registries.insert(namespace, TypstCoreRegistry::Template(template));
+    }
+
+    /// Serves a namespace through a JS callback, which receives the `TypstCorePackage`
+    /// and may return a URL or a promise of the tarball bytes.
+    pub fn set_registry_resolver(&mut self, namespace: String, resolver: Function) {
+        self.registries.insert(namespace, TypstCoreRegistry::resolver(resolver));
+    }
+
+    pub fn remove_registry(&mut self, namespace: String) {
+        self.registries.remove(&namespace);
+    }
+
+    /// Installs a package from the bytes of its `.tar.gz` archive.
+    pub fn install_package(&mut self, package: TypstCorePackage, tarball: Vec<u8>) -> Result<(), TypstCoreError> {
+        let spec = PackageSpec::try_from(&package)?;
+        self.install_package_tarball(&spec, tarball)?;
+        Ok(())
     }
 
     pub fn add_source(&mut self, path: String, content: String) {
@@ -135,25 +524,31 @@ impl TypstCore {
         self.sources.borrow_mut().remove(&id);
     }
 
+    /// Adds a binary file such as an image, data file or plugin to the virtual file system.
+    pub fn add_file(&mut self, path: String, content: Vec<u8>) {
+        let id = FileId::new(None, VirtualPath::new(&path));
+        self.files.borrow_mut().insert(id, Bytes::new(content));
+    }
+
+    pub fn remove_file(&mut self, path: String) {
+        let id = FileId::new(None, VirtualPath::new(&path));
+        self.files.borrow_mut().remove(&id);
+    }
+
     pub fn edit_source(
         &mut self,
         path: String,
         content: String,
         monaco_range: MonacoRange,
     ) -> Result<(), TypstCoreError> {
-        let id = FileId::new(None, VirtualPath::new(&path));
+        let (source, typst_range) = self.range_at(&path, &monaco_range)?;
         let mut sources = self.sources.borrow_mut();
-        if let Some(source) = sources.get_mut(&id) {
-            let typst_range = monaco_range.to_typst_range(&source.source);
-
+        if let Some(source) = sources.get_mut(&source.id()) {
             let range = source.source.edit(typst_range, &content);
             console_log!("Edited range: {:?}; New text: {:?}", range, source.source.text());
             Ok(())
         } else {
-            Err(typst_error!(format!(
-                "Failed to edit source, source not found for path: {:?}",
-                path
-            )))
+            Err(TypstCoreError::file_not_found(path))
         }
     }
 
@@ -163,50 +558,23 @@ impl TypstCore {
         if let Some(source) = sources.get(&id) {
             Ok(source.source.text().to_string())
         } else {
-            Err(typst_error!(format!(
-                "Failed to get source, source not found for path: {:?}",
-                path
-            )))
+            Err(TypstCoreError::file_not_found(path))
         }
     }
 
+    /// Completes the code at the given position, replacing the text from the start of the returned ranges.
     pub fn auto_complete(
         &self,
         path: String,
         line: usize,
         column: usize,
-    ) -> Result<Vec<JsValue>, TypstCoreError> {
-        let id = FileId::new(None, VirtualPath::new(&path));
-        let sources = self.sources.borrow();
-        if let Some(source) = sources.get(&id) {
-            let typst_position = MonacoPosition::new(line, column).to_typst_position(&source.source);
-            if let Some(typst_position) = typst_position {
-                let doc = self.last_doc.lock().unwrap();
-                
-                match typst_ide::autocomplete(self, doc.as_ref(), &source.source, typst_position, true) {
-                    Some(completions) => {
-                        let mut result = Vec::new();
-                        for completion in completions.1 {
-                            if let Ok(completion) = serde_wasm_bindgen::to_value(&completion) {
-                                result.push(completion);
-                            }
-                        }
-                        Ok(result)
-                    }
-                    None => Ok(Vec::new()),
-                }
-            } else {
-                Err(typst_error!(format!(
-                    "Failed to convert Monaco position to Typst position for path: {:?}",
-                    path
-                )))
-            }
-        } else {
-            Err(typst_error!(format!(
-                "Failed to get source, source not found for path: {:?}",
-                path
-            )))
-        }
+    ) -> Result<Vec<TypstCoreCompletion>, TypstCoreError> {
+        let (source, cursor) = self.source_at(&path, &MonacoPosition::new(line, column))?;
+
+        let (doc, main) = self.focus(source.id());
+        Ok(typst_ide::autocomplete(&self.focused(main), doc.as_ref(), &source, cursor, true)
+            .map(|completions| TypstCoreCompletion::from_completions(completions, cursor, &source))
+            .unwrap_or_default())
     }
 
     pub fn definition(
@@ -214,22 +582,265 @@ impl TypstCore {
         path: String,
         range: MonacoRange,
     ) -> Result<TypstCoreDefinition, TypstCoreError> {
-        let id = FileId::new(None, VirtualPath::new(&path));
-        let sources = self.sources.borrow();
-        if let Some(source) = sources.get(&id) {
-            let typst_range = range.to_typst_range(&source.source);
-            
-            let doc = self.last_doc.lock().unwrap().clone();
+        let (source, typst_range) = self.range_at(&path, &range)?;
 
-            let typst_def = typst_ide::definition(self, doc.as_ref(), &source.source(), typst_range.start, typst::syntax::Side::After);
-            let typst_tt = typst_ide::tooltip(self, doc.as_ref(), &source.source(), typst_range.start, typst::syntax::Side::After);
+        let (doc, main) = self.focus(source.id());
+        let world = self.focused(main);
 
-            TypstCoreDefinition::new(self, typst_def, typst_tt, source)
-        } else {
-            Err(typst_error!(format!(
-                "Failed to get source, source not found for path: {:?}",
-                path
-            )))
+        let typst_def = typst_ide::definition(&world, doc.as_ref(), &source, typst_range.start, typst::syntax::Side::After);
+        let typst_tt = typst_ide::tooltip(&world, doc.as_ref(), &source, typst_range.start, typst::syntax::Side::After);
+
+        TypstCoreDefinition::new(self, typst_def, typst_tt)
+    }
+
+    /// Describes the node at the given position, e.g. the evaluated value of `#x`, the docs of a std function or
+    /// the doc comment of a user binding.
+    pub fn hover(
+        &self,
+        path: String,
+        position: MonacoPosition,
+    ) -> Result<Option<TypstCoreHover>, TypstCoreError> {
+        let (source, cursor) = self.source_at(&path, &position)?;
+
+        let (doc, main) = self.focus(source.id());
+        let tooltip = typst_ide::tooltip(&self.focused(main), doc.as_ref(), &source, cursor, Side::After);
+        let docs = self.binding_docs(&source, cursor).filter(|docs| !docs.is_empty());
+        if tooltip.is_none() && docs.is_none() {
+            return Ok(None);
         }
+
+        let range = LinkedNode::new(source.root())
+            .leaf_at(cursor, Side::After)
+            .map(|leaf| MonacoRange::from_typst_range(leaf.range(), &source))
+            .unwrap_or_default();
+
+        Ok(Some(TypstCoreHover::new(tooltip.as_ref(), docs.as_ref(), range)))
+    }
+
+    /// Describes the parameters of the function call around the given position and which one is being typed.
+    pub fn signature_help(
+        &self,
+        path: String,
+        position: MonacoPosition,
+    ) -> Result<Option<TypstCoreSignatureHelp>, TypstCoreError> {
+        let (source, cursor) = self.source_at(&path, &position)?;
+        TypstCoreSignatureHelp::new(self, &source, cursor)
+    }
+
+    /// Finds every reference to the binding at the given position across all sources, including the binding itself.
+    pub fn references(
+        &self,
+        path: String,
+        position: MonacoPosition,
+    ) -> Result<Vec<TypstCoreRange>, TypstCoreError> {
+        Ok(self
+            .reference_ranges(&path, &position)?
+            .into_iter()
+            .map(|(source, range)| TypstCoreRange {
+                monaco_range: MonacoRange::from_typst_range(range, &source),
+                ..TypstCoreRange::in_file(source.id())
+            })
+            .collect())
+    }
+
+    /// Renames the binding at the given position and all its references.
+    /// Returns the edits per file and applies them to the sources if `apply` is set.
+    pub fn rename(
+        &mut self,
+        path: String,
+        position: MonacoPosition,
+        new_name: String,
+        apply: bool,
+    ) -> Result<Vec<TypstCoreFileEdit>, TypstCoreError> {
+        if !typst::syntax::is_ident(&new_name) {
+            return Err(typst_error!(format!("{:?} is not a valid identifier", new_name)));
+        }
+
+        let references = self.reference_ranges(&path, &position)?;
+        if references.is_empty() {
+            return Err(typst_error!(format!("No binding to rename at {}:{}:{}", path, position.line_number, position.column)));
+        }
+        if let Some(package) = references.iter().find_map(|(source, _)| source.id().package()) {
+            return Err(typst_error!(format!("Cannot rename a binding of package {}", package)));
+        }
+
+        let ranges = references
+            .iter()
+            .map(|(source, range)| TypstCoreRange {
+                monaco_range: MonacoRange::from_typst_range(range.clone(), source),
+                ..TypstCoreRange::in_file(source.id())
+            })
+            .collect();
+        let edits = TypstCoreFileEdit::replace_all(ranges, &new_name);
+        if apply {
+            // The byte ranges of a file are sorted, applying them back to front keeps the earlier ones valid.
+            let mut sources = self.sources.borrow_mut();
+            for (source, range) in references.into_iter().rev() {
+                if let Some(source_file) = sources.get_mut(&source.id()) {
+                    source_file.source.edit(range, &new_name);
+                }
+            }
+        }
+        Ok(edits)
+    }
+
+    /// Lists the headings, bindings, labels, figures and rules of a source, together with the
+    /// heading outline of the last document of the target the source belongs to.
+    pub fn document_symbols(&self, path: String) -> Result<TypstCoreDocumentSymbols, TypstCoreError> {
+        let source = self.user_source(&path)?;
+
+        let (doc, _) = self.focus(source.id());
+        let outline = doc
+            .map(|doc| TypstCoreOutlineItem::from_document(self, &doc, &self.sources.borrow()))
+            .unwrap_or_default();
+
+        Ok(TypstCoreDocumentSymbols {
+            symbols: TypstCoreDocumentSymbol::from_source(&source),
+            outline,
+        })
+    }
+
+    /// Finds what a click at the given point of a one-indexed page of the default target leads to.
+    /// The point is in typographic points from the top left corner, like the coordinates of the SVG output.
+    pub fn jump_from_click(&self, page: usize, x: f64, y: f64) -> Result<Option<TypstCoreJump>, TypstCoreError> {
+        self.jump_from_click_target(DEFAULT_TARGET.to_owned(), page, x, y)
+    }
+
+    /// Finds what a click at the given point of a one-indexed page of the named target leads to.
+    pub fn jump_from_click_target(
+        &self,
+        target: String,
+        page: usize,
+        x: f64,
+        y: f64,
+    ) -> Result<Option<TypstCoreJump>, TypstCoreError> {
+        let doc = &self.target_doc(&target)?;
+        let frame = &page
+            .checked_sub(1)
+            .and_then(|index| doc.pages.get(index))
+            .ok_or_else(|| typst_error!(format!("Page {} does not exist", page)))?
+            .frame;
+
+        let click = Point::new(Abs::pt(x), Abs::pt(y));
+        Ok(typst_ide::jump_from_click(self, doc, frame, click)
+            .and_then(|jump| TypstCoreJump::new(jump, &self.sources.borrow())))
+    }
+
+    /// Finds the points showing the text at the given position, in the last document of the target containing the source.
+    pub fn jump_from_cursor(
+        &self,
+        path: String,
+        position: MonacoPosition,
+    ) -> Result<Vec<TypstCorePagePosition>, TypstCoreError> {
+        let (source, cursor) = self.source_at(&path, &position)?;
+        let (doc, _) = self.focus(source.id());
+        let doc = doc
+            .as_ref()
+            .ok_or_else(|| typst_error!("No document has been compiled yet"))?;
+
+        Ok(typst_ide::jump_from_cursor(doc, &source, cursor)
+            .into_iter()
+            .map(TypstCorePagePosition::from)
+            .collect())
+    }
+
+    /// Formats a source, returns no edits if it has syntax errors.
+    pub fn format(&self, path: String) -> Result<Vec<TypstCoreTextEdit>, TypstCoreError> {
+        self.format_edits(&path, None)
+    }
+
+    /// Formats the parts of a source intersecting the range, returns no edits if it has syntax errors.
+    pub fn format_range(&self, path: String, range: MonacoRange) -> Result<Vec<TypstCoreTextEdit>, TypstCoreError> {
+        self.format_edits(&path, Some(range))
+    }
+
+    /// The token types and modifiers used by `semantic_tokens`.
+    pub fn semantic_tokens_legend() -> TypstCoreSemanticTokensLegend {
+        TypstCoreSemanticTokensLegend::default()
+    }
+
+    /// Computes the semantic tokens of a source, encoded for Monaco.
+    pub fn semantic_tokens(&self, path: String) -> Result<TypstCoreSemanticTokens, TypstCoreError> {
+        let source = self.user_source(&path)?;
+        let data = encode_tokens(&source);
+        let tokens = TypstCoreSemanticTokens {
+            result_id: format!("{:032x}", hash128(&data)),
+            data,
+        };
+
+        self.semantic_tokens.lock().unwrap().insert(source.id(), tokens.clone());
+        Ok(tokens)
+    }
+
+    /// Computes the semantic tokens of a source as edits to the previous result with the given id.
+    pub fn semantic_tokens_delta(
+        &self,
+        path: String,
+        previous_result_id: String,
+    ) -> Result<TypstCoreSemanticTokensDelta, TypstCoreError> {
+        let id = FileId::new(None, VirtualPath::new(&path));
+        let previous = self
+            .semantic_tokens
+            .lock()
+            .unwrap()
+            .get(&id)
+            .filter(|previous| previous.result_id == previous_result_id)
+            .cloned();
+        let tokens = self.semantic_tokens(path)?;
+
+        Ok(match previous {
+            Some(previous) => TypstCoreSemanticTokensDelta {
+                result_id: tokens.result_id,
+                edits: TypstCoreSemanticTokensEdit::between(&previous.data, &tokens.data)
+                    .into_iter()
+                    .collect(),
+                data: None,
+            },
+            None => TypstCoreSemanticTokensDelta {
+                result_id: tokens.result_id,
+                edits: Vec::new(),
+                data: Some(tokens.data),
+            },
+        })
+    }
+
+    /// Lists the foldable blocks, multi-line calls, raw blocks, block comments and heading sections of a source.
+    pub fn folding_ranges(&self, path: String) -> Result<Vec<TypstCoreFoldingRange>, TypstCoreError> {
+        let source = self.user_source(&path)?;
+        Ok(TypstCoreFoldingRange::from_source(&source))
+    }
+
+    /// Lists the ranges of the syntax nodes around each position, for expanding the selection.
+    pub fn selection_ranges(
+        &self,
+        path: String,
+        positions: Vec<MonacoPosition>,
+    ) -> Result<Vec<TypstCoreSelectionRange>, TypstCoreError> {
+        positions
+            .iter()
+            .map(|position| {
+                let (source, cursor) = self.source_at(&path, position)?;
+                Ok(TypstCoreSelectionRange::new(&source, cursor))
+            })
+            .collect()
+    }
+
+    /// Suggests fixes for the given diagnostics within the range, such as importing an unknown variable from
+    /// another file or creating a missing included file, and ways to wrap the selected markup.
+    pub fn code_actions(
+        &self,
+        path: String,
+        range: MonacoRange,
+        diagnostics: Vec<TypstCoreDiagnostics>,
+    ) -> Result<Vec<TypstCoreCodeAction>, TypstCoreError> {
+        let (source, range) = self.range_at(&path, &range)?;
+        Ok(TypstCoreCodeAction::collect(self, &source, range, &diagnostics))
+    }
+
+    /// Documents the top-level bindings of a module and the ones it re-exports from other files, using their
+    /// doc comments. Returns the structured docs together with a rendered reference page.
+    pub fn generate_docs(&self, path: String) -> Result<TypstCoreModuleDocs, TypstCoreError> {
+        let source = self.user_source(&path)?;
+        Ok(TypstCoreModuleDocs::new(&source, &self.sources.borrow()))
     }
 }
@@ -1,6 +1,8 @@
+use std::sync::OnceLock;
+
 use chrono::{Datelike, Local, Timelike};
 use typst::{
-    diag::FileResult, foundations::{Bytes, Datetime}, syntax::{FileId, Source}, text::{Font, FontBook}, utils::LazyHash, Feature, Library, World
+    diag::FileResult, foundations::{Bytes, Datetime}, syntax::{FileId, Source, VirtualPath}, text::{Font, FontBook}, utils::LazyHash, Feature, Library, World
 };
 use typst_ide::IdeWorld;
 
@@ -12,7 +14,7 @@ impl World for TypstCore {
     #[doc = " Can be created through `Library::build()`."]
     fn library(&self) -> &LazyHash<Library> {
         self.library
-            .get_or_init(|| LazyHash::new(Library::builder().with_features(vec![Feature::Html].iter().cloned().collect()).build()))
+            .get_or_init(|| LazyHash::new(Library::builder().with_features([Feature::Html].into_iter().collect()).build()))
     }
 
     #[doc = " Metadata about all known fonts."]
@@ -23,17 +25,25 @@ impl World for TypstCore {
 
     #[doc = " Get the file id of the main source file."]
     fn main(&self) -> FileId {
-        *self.root.as_ref().expect("Root path is not set")
+        // Only unset before anything was compiled or queried, the fake file then fails to load instead of panicking.
+        static UNSET: OnceLock<FileId> = OnceLock::new();
+        self.main
+            .lock()
+            .unwrap()
+            .unwrap_or_else(|| *UNSET.get_or_init(|| FileId::new_fake(VirtualPath::new("/main.typ"))))
     }
 
     #[doc = " Try to access the specified source file."]
     fn source(&self, id: FileId) -> FileResult<Source> {
-        Ok(self.retrieve_source(id)?.source())
+        if let Some(accessed) = self.accessed.lock().unwrap().as_mut() {
+            accessed.insert(id);
+        }
+        self.record_failure(id, self.retrieve_source(id)).map(|source_file| source_file.source())
     }
 
     #[doc = " Try to access the specified file."]
     fn file(&self, id: FileId) -> FileResult<Bytes> {
-        Ok(self.retrieve_source(id)?.bytes())
+        self.record_failure(id, self.retrieve_file(id))
     }
 
     #[doc = " Try to access the font with the given index in the font book."]
@@ -75,4 +85,54 @@ impl IdeWorld for TypstCore {
     fn upcast(&self) -> &dyn World {
         self
     }
+}
+
+/// The core with another main file, so IDE queries can trace the target a file belongs to without changing
+/// the main file of the next compile.
+pub struct FocusedWorld<'a> {
+    core: &'a TypstCore,
+    main: FileId,
+}
+
+impl TypstCore {
+    /// Views the core with the given file as main, see `focus`.
+    pub fn focused(&self, main: FileId) -> FocusedWorld<'_> {
+        FocusedWorld { core: self, main }
+    }
+}
+
+impl World for FocusedWorld<'_> {
+    fn library(&self) -> &LazyHash<Library> {
+        self.core.library()
+    }
+
+    fn book(&self) -> &LazyHash<FontBook> {
+        self.core.book()
+    }
+
+    fn main(&self) -> FileId {
+        self.main
+    }
+
+    fn source(&self, id: FileId) -> FileResult<Source> {
+        self.core.source(id)
+    }
+
+    fn file(&self, id: FileId) -> FileResult<Bytes> {
+        self.core.file(id)
+    }
+
+    fn font(&self, index: usize) -> Option<Font> {
+        self.core.font(index)
+    }
+
+    fn today(&self, offset: Option<i64>) -> Option<Datetime> {
+        self.core.today(offset)
+    }
+}
+
+impl IdeWorld for FocusedWorld<'_> {
+    fn upcast(&self) -> &dyn World {
+        self
+    }
 }
\ No newline at end of file
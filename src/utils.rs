@@ -1,14 +1,16 @@
-use wasm_bindgen::prelude::wasm_bindgen;
-
-#[wasm_bindgen]
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen::prelude::wasm_bindgen]
 extern "C" {
     // Use `js_namespace` here to bind `console.log(..)` instead of just
     // `log(..)`
     #[wasm_bindgen(js_namespace = console)]
     pub fn log(s: &str);
+}
 
-    #[wasm_bindgen(js_namespace = typst_core_utils)]
-    pub fn fetch(s: &str) -> Vec<u8>;
+/// Prints to stdout instead, so the crate and its tests also run natively.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn log(s: &str) {
+    println!("{}", s);
 }
 
 #[macro_export]